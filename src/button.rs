@@ -0,0 +1,434 @@
+use crate::colors::{Color, BLACK, WHITE};
+use crate::entities::{Entity, EntityBuilder, ShapeKind, Vertex};
+use crate::marlin::{MasterWindowState, SceneName};
+use crate::pipeline::TextureId;
+use crate::text::{Align, Text, Typesetter};
+
+const TOOLTIP_SIZE: f32 = 14.0;
+const TOOLTIP_MAX_WIDTH: f32 = 220.0;
+// between the tooltip's text and the edge of its box, and between the box and
+// the button above it
+const TOOLTIP_PADDING: f32 = 5.0;
+const TOOLTIP_GAP: f32 = 6.0;
+
+// which of a MasterWindowState's buttons, in the order they were added
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ButtonId(pub(crate) usize);
+
+// what a button does when it is clicked
+pub enum ButtonAction {
+    Navigate(SceneName),
+    // anything else, from toggling a setting to restarting a simulation
+    Run(Box<dyn FnMut(&mut MasterWindowState)>)
+}
+
+impl ButtonAction {
+
+    pub fn run<F: FnMut(&mut MasterWindowState) + 'static>(action: F) -> ButtonAction {
+        Self::Run(Box::new(action))
+    }
+
+}
+
+impl From<SceneName> for ButtonAction {
+    fn from(scene: SceneName) -> Self {
+        Self::Navigate(scene)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ButtonState {
+    Normal,
+    Hovered,
+    Pressed,
+    Disabled
+}
+
+// the fill of a button in each state, and the color its label turns when it is
+// disabled
+#[derive(Clone, Copy, PartialEq)]
+pub struct ButtonStyle {
+    pub normal: Color,
+    pub hovered: Color,
+    pub pressed: Color,
+    pub disabled: Color,
+    pub disabled_label: Color
+}
+
+impl ButtonStyle {
+
+    // hovering tints `normal` towards gray, pressing darkens it and disabling
+    // washes it out
+    pub fn from_color(normal: Color) -> ButtonStyle {
+        let gray = Color::new(128, 128, 128);
+        Self {
+            normal,
+            hovered: Color::mix(&[normal, normal, normal, gray]),
+            pressed: Color::mix(&[normal, BLACK]),
+            disabled: Color::mix(&[normal, gray, gray, gray]),
+            disabled_label: Color::new(90, 90, 90)
+        }
+    }
+
+    pub fn fill(&self, state: ButtonState) -> Color {
+        match state {
+            ButtonState::Normal => self.normal,
+            ButtonState::Hovered => self.hovered,
+            ButtonState::Pressed => self.pressed,
+            ButtonState::Disabled => self.disabled
+        }
+    }
+
+}
+
+pub struct Button {
+    inhabiting_scene: SceneName,
+    // what clicks are tested against; circles exactly, anything else by the
+    // triangles it was tessellated into
+    shape: ShapeKind,
+    // drawn over, and clicked before, the buttons of lower layers
+    layer: i32,
+    // of the bounding box; labels and tooltips are centered on it, and
+    // tooltips hang from its bottom
+    center: [f32; 2],
+    bottom: f32,
+    // None only while it is running
+    action: Option<ButtonAction>,
+    entity: Entity,
+    style: ButtonStyle,
+    enabled: bool,
+    hovered: bool,
+    pressed: bool,
+    // always centered on the button, whatever its own alignment
+    label: Option<Text>,
+    tooltip: Option<String>,
+    // typesetting needs the window state's glyph atlas, so the label and
+    // tooltip are only turned into entities when `typeset` is next called
+    typeset: bool,
+    label_entity: Option<Entity>,
+    tooltip_entities: Vec<Entity>
+}
+
+impl Button {
+
+    // styled after the color of the entity's first vertex; `entity` is the
+    // tessellated `shape`
+    pub fn new(inhabiting_scene: SceneName, action: ButtonAction, shape: ShapeKind, entity: Entity) -> Button {
+
+        let (min, max) = entity.vertices.iter().fold(([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]), |(min, max), vertex| {
+            let [x, y, _] = vertex.position;
+            ([min[0].min(x), min[1].min(y)], [max[0].max(x), max[1].max(y)])
+        });
+
+        let style = ButtonStyle::from_color(entity.vertices[0].color.into());
+
+        let mut button = Self {
+            inhabiting_scene,
+            shape,
+            layer: 0,
+            center: [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0],
+            bottom: min[1],
+            action: Some(action),
+            entity,
+            style,
+            enabled: true,
+            hovered: false,
+            pressed: false,
+            label: None,
+            tooltip: None,
+            typeset: true,
+            label_entity: None,
+            tooltip_entities: vec![]
+        };
+        button.restyle();
+        button
+    }
+
+    // `point` in canvas coordinates
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        match self.shape {
+            ShapeKind::Circle(radius) => {
+                let [x, y] = self.center;
+                (point[0] - x).hypot(point[1] - y) <= radius
+            },
+            _ => self.entity.contains(point)
+        }
+    }
+
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    pub fn set_layer(&mut self, layer: i32) {
+        self.layer = layer;
+    }
+
+    pub fn scene(&self) -> SceneName {
+        self.inhabiting_scene
+    }
+
+    pub fn set_action(&mut self, action: ButtonAction) {
+        self.action = Some(action);
+    }
+
+    // lends the action out to be run against the state that owns the button
+    pub fn take_action(&mut self) -> Option<ButtonAction> {
+        self.action.take()
+    }
+
+    // hands a lent action back, unless it was replaced while it ran
+    pub fn return_action(&mut self, action: ButtonAction) {
+        self.action.get_or_insert(action);
+    }
+
+    pub fn state(&self) -> ButtonState {
+        if !self.enabled {
+            ButtonState::Disabled
+        } else if self.pressed {
+            ButtonState::Pressed
+        } else if self.hovered {
+            ButtonState::Hovered
+        } else {
+            ButtonState::Normal
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        if self.enabled != enabled {
+            self.enabled = enabled;
+            self.pressed &= enabled;
+            self.typeset = false;
+            self.restyle();
+        }
+    }
+
+    pub fn set_style(&mut self, style: ButtonStyle) {
+        self.style = style;
+        self.typeset = false;
+        self.restyle();
+    }
+
+    pub fn set_label(&mut self, label: Option<Text>) {
+        self.label = label;
+        self.typeset = false;
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: Option<&str>) {
+        self.tooltip = tooltip.map(str::to_string);
+        self.typeset = false;
+    }
+
+    pub fn set_hovered(&mut self, hovered: bool) {
+        self.hovered = hovered;
+        self.restyle();
+    }
+
+    // disabled buttons can't be pressed
+    pub fn press(&mut self) {
+        self.pressed = self.enabled;
+        self.restyle();
+    }
+
+    // whether the button was pressed until now
+    pub fn release(&mut self) -> bool {
+        let pressed = std::mem::take(&mut self.pressed);
+        self.restyle();
+        pressed
+    }
+
+    fn restyle(&mut self) {
+        let fill = self.style.fill(self.state()).in_percentages();
+        for vertex in &mut self.entity.vertices {
+            vertex.color = fill;
+        }
+    }
+
+    // brings the label and tooltip entities up to date; `texture` must hold
+    // the typesetter's atlas
+    pub fn typeset(&mut self, typesetter: &mut Typesetter, texture: TextureId) {
+
+        if self.typeset {
+            return;
+        }
+        self.typeset = true;
+        let [x, y] = self.center;
+
+        self.label_entity = self.label.as_ref().and_then(|label| {
+            let mut label = label.clone().align(Align::Center);
+            if !self.enabled {
+                label = label.color(self.style.disabled_label);
+            }
+            let height = typesetter.measure(&label)[1];
+            typesetter.entity(&label, [x, y + height / 2.0], texture)
+        });
+
+        self.tooltip_entities = vec![];
+        if let Some(tooltip) = &self.tooltip {
+            let text = Text::new(tooltip, TOOLTIP_SIZE).color(WHITE).max_width(TOOLTIP_MAX_WIDTH).align(Align::Center);
+            let [width, height] = typesetter.measure(&text);
+            let top = self.bottom - TOOLTIP_GAP;
+            let (left, right) = (x - width / 2.0 - TOOLTIP_PADDING, x + width / 2.0 + TOOLTIP_PADDING);
+            let bottom = top - height - 2.0 * TOOLTIP_PADDING;
+            let corner = |x: f32, y: f32| Vertex::new(x, y, 0.0, Color::new(40, 40, 40));
+            self.tooltip_entities.push(
+                EntityBuilder::from_shape(ShapeKind::Rectangle, vec![corner(left, top), corner(left, bottom), corner(right, bottom), corner(right, top)])
+                    .unwrap()
+                    .build()
+            );
+            self.tooltip_entities.extend(typesetter.entity(&text, [x, top - TOOLTIP_PADDING], texture));
+        }
+    }
+
+    // the button and its label
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        std::iter::once(&self.entity).chain(&self.label_entity)
+    }
+
+    // drawn over every button while this one is hovered
+    pub fn tooltip(&self) -> &[Entity] {
+        if self.hovered {
+            &self.tooltip_entities
+        } else {
+            &[]
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::entities::ShapeError;
+
+    fn button() -> Button {
+        let square = vec![
+            Vertex::new(-10.0, 10.0, 0.0, WHITE),
+            Vertex::new(-10.0, -10.0, 0.0, WHITE),
+            Vertex::new(10.0, -10.0, 0.0, WHITE),
+            Vertex::new(10.0, 10.0, 0.0, WHITE)
+        ];
+        let entity = EntityBuilder::from_shape(ShapeKind::Rectangle, square).unwrap().build();
+        Button::new(SceneName::Home, SceneName::Grapher.into(), ShapeKind::Rectangle, entity)
+    }
+
+    #[test]
+    fn clicks_land_on_the_shape_not_its_bounds() {
+        let vertices = vec![Vertex::new(-10.0, -10.0, 0.0, WHITE), Vertex::new(10.0, -10.0, 0.0, WHITE), Vertex::new(0.0, 10.0, 0.0, WHITE)];
+        let entity = EntityBuilder::from_shape(ShapeKind::Triangle, vertices).unwrap().build();
+        let triangle = Button::new(SceneName::Home, SceneName::Grapher.into(), ShapeKind::Triangle, entity);
+        assert!(triangle.contains([0.0, 9.0]) && triangle.contains([-10.0, -10.0]));
+        assert!(!triangle.contains([-9.0, 9.0]));
+
+        let shape = ShapeKind::Circle(10.0);
+        let entity = EntityBuilder::from_shape(shape, vec![Vertex::new(5.0, 5.0, 0.0, WHITE)]).unwrap().build();
+        let circle = Button::new(SceneName::Home, SceneName::Grapher.into(), shape, entity);
+        // right on the rim, between two of the tessellated segments
+        let rim = std::f32::consts::PI / 360.0;
+        assert!(circle.contains([5.0 + 10.0 * rim.cos(), 5.0 + 10.0 * rim.sin()]));
+        assert!(!circle.contains([12.5, 12.5]));
+    }
+
+    #[test]
+    fn flat_triangles_cover_nothing() {
+        let vertices = vec![Vertex::new(0.0, 0.0, 0.0, WHITE), Vertex::new(10.0, 10.0, 0.0, WHITE), Vertex::new(20.0, 20.0, 0.0, WHITE)];
+        assert!(matches!(EntityBuilder::from_shape(ShapeKind::Triangle, vertices.clone()), Err(ShapeError::Degenerate)));
+        let entity = EntityBuilder::from_triangles(vertices).build();
+        let flat = Button::new(SceneName::Home, SceneName::Grapher.into(), ShapeKind::Triangle, entity);
+        // on the line through the vertices, far from all of them
+        assert!(!flat.contains([1000.0, 1000.0]) && !flat.contains([10.0, 10.0]));
+    }
+
+    #[test]
+    fn states_follow_the_mouse() {
+        let mut button = button();
+        let fill = |button: &Button| Color::from(button.entity.vertices[0].color);
+        assert!(fill(&button) == WHITE);
+        button.set_hovered(true);
+        assert_eq!(button.state(), ButtonState::Hovered);
+        button.press();
+        assert_eq!(button.state(), ButtonState::Pressed);
+        assert!(fill(&button) == button.style.pressed);
+        assert!(button.release());
+        assert_eq!(button.state(), ButtonState::Hovered);
+        assert!(!button.release());
+    }
+
+    #[test]
+    fn disabled_buttons_ignore_presses() {
+        let mut button = button();
+        button.set_hovered(true);
+        button.press();
+        button.set_enabled(false);
+        assert_eq!(button.state(), ButtonState::Disabled);
+        assert!(!button.release());
+        button.press();
+        assert!(!button.is_pressed());
+        button.set_enabled(true);
+        assert_eq!(button.state(), ButtonState::Hovered);
+    }
+
+    #[test]
+    fn tooltips_show_while_hovered() {
+        let mut button = button();
+        button.set_label(Some(Text::new("Go", 12.0)));
+        button.set_tooltip(Some("Opens the grapher"));
+        button.typeset(&mut Typesetter::new(), TextureId(0));
+        assert_eq!(button.entities().count(), 2);
+        assert!(button.tooltip().is_empty());
+        button.set_hovered(true);
+        assert_eq!(button.tooltip().len(), 2);
+        // the tooltip's box hangs below the button
+        let top = button.tooltip()[0].vertices.iter().map(|v| v.position[1]).fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(top, -10.0 - TOOLTIP_GAP);
+    }
+
+    #[test]
+    fn actions_run_when_clicked() {
+        let mut state = MasterWindowState::software(160, 120);
+        let square = |x: f32| vec![
+            Vertex::new(x, 10.0, 0.0, WHITE),
+            Vertex::new(x, -10.0, 0.0, WHITE),
+            Vertex::new(x + 20.0, -10.0, 0.0, WHITE),
+            Vertex::new(x + 20.0, 10.0, 0.0, WHITE)
+        ];
+        state.add_button(&SceneName::Home, &ShapeKind::Rectangle, square(-50.0), SceneName::Simulation);
+        state.add_button(&SceneName::Simulation, &ShapeKind::Rectangle, square(30.0), ButtonAction::run(|state| {
+            state.simulation_mut().set_timestep(0.5);
+        }));
+        state.click([-40.0, 0.0]);
+        assert!(state.current_scene() == SceneName::Simulation);
+        state.click([40.0, 0.0]);
+        assert_eq!(state.simulation().timestep(), 0.5);
+    }
+
+    #[test]
+    fn topmost_button_takes_the_click() {
+        let mut state = MasterWindowState::software(160, 120);
+        let raised = state.add_button(&SceneName::Home, &ShapeKind::Circle(30.0), vec![Vertex::new(-15.0, 0.0, 0.0, WHITE)], SceneName::Grapher);
+        state.button_mut(raised).set_layer(1);
+        state.add_button(&SceneName::Home, &ShapeKind::Triangle, vec![
+            Vertex::new(-10.0, -40.0, 0.0, WHITE),
+            Vertex::new(60.0, -40.0, 0.0, WHITE),
+            Vertex::new(-10.0, 40.0, 0.0, WHITE)
+        ], SceneName::Simulation);
+        // within the circle's bounding box, but on neither shape
+        state.click([13.0, 25.0]);
+        assert!(state.current_scene() == SceneName::Home);
+        state.click([12.0, -20.0]);
+        assert!(state.current_scene() == SceneName::Simulation);
+        state.set_scene(SceneName::Home);
+        state.click([0.0, 0.0]);
+        assert!(state.current_scene() == SceneName::Grapher);
+    }
+
+}
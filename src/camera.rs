@@ -0,0 +1,166 @@
+use crate::transform::Transform;
+
+// zoom is clamped so the view can neither collapse to a point nor lose all precision
+const MIN_ZOOM: f32 = 1e-4;
+const MAX_ZOOM: f32 = 1e4;
+
+// Looks at `position` in world space, magnified by `zoom` and turned by
+// `rotation` radians counter-clockwise. Canvas coordinates are the logical
+// pixels around the middle of the window that `MousePosition` reports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    pub position: [f32; 2],
+    zoom: f32,
+    pub rotation: f32
+}
+
+impl Camera {
+
+    pub fn new() -> Camera {
+        Self {
+            position: [0.0, 0.0],
+            zoom: 1.0,
+            rotation: 0.0
+        }
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+
+    // world space => canvas space
+    pub fn view(&self) -> Transform {
+        Transform::translation(-self.position[0], -self.position[1])
+            .then(&Transform::rotation(-self.rotation))
+            .then(&Transform::scale(self.zoom, self.zoom))
+    }
+
+    pub fn canvas_to_world(&self, canvas: [f32; 2]) -> [f32; 2] {
+        self.view().inverse().apply(canvas)
+    }
+
+    pub fn world_to_canvas(&self, world: [f32; 2]) -> [f32; 2] {
+        self.view().apply(world)
+    }
+
+    // bounding box in world space of a canvas `size` logical pixels across
+    pub fn visible_region(&self, size: [f32; 2]) -> ([f32; 2], [f32; 2]) {
+        let (half_width, half_height) = (size[0] / 2.0, size[1] / 2.0);
+        let corners = [
+            [-half_width, -half_height],
+            [half_width, -half_height],
+            [half_width, half_height],
+            [-half_width, half_height]
+        ].map(|corner| self.canvas_to_world(corner));
+        let mut min = corners[0];
+        let mut max = corners[0];
+        for corner in &corners[1..] {
+            for i in 0..2 {
+                min[i] = min[i].min(corner[i]);
+                max[i] = max[i].max(corner[i]);
+            }
+        }
+        (min, max)
+    }
+
+    // moves the camera so the world point under `from` ends up under `to`
+    pub fn pan(&mut self, from: [f32; 2], to: [f32; 2]) {
+        let before = self.canvas_to_world(from);
+        let after = self.canvas_to_world(to);
+        self.position[0] += before[0] - after[0];
+        self.position[1] += before[1] - after[1];
+    }
+
+    // scales the zoom by `factor` while keeping the world point under `anchor` fixed
+    pub fn zoom_about(&mut self, factor: f32, anchor: [f32; 2]) {
+        let before = self.canvas_to_world(anchor);
+        self.set_zoom(self.zoom * factor);
+        let after = self.canvas_to_world(anchor);
+        self.position[0] += before[0] - after[0];
+        self.position[1] += before[1] - after[1];
+    }
+
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-3 && (a[1] - b[1]).abs() < 1e-3
+    }
+
+    fn camera() -> Camera {
+        let mut camera = Camera::new();
+        camera.position = [40.0, -25.0];
+        camera.set_zoom(2.5);
+        camera.rotation = 0.7;
+        camera
+    }
+
+    #[test]
+    fn canvas_and_world_round_trip() {
+        let camera = camera();
+        for point in [[0.0, 0.0], [120.0, -80.0], [-300.0, 210.0]] {
+            assert!(close(camera.world_to_canvas(camera.canvas_to_world(point)), point));
+            assert!(close(camera.canvas_to_world(camera.world_to_canvas(point)), point));
+        }
+        // the middle of the canvas shows the camera's position
+        assert!(close(camera.canvas_to_world([0.0, 0.0]), camera.position));
+    }
+
+    #[test]
+    fn zooming_keeps_the_anchor_in_place() {
+        let mut camera = camera();
+        let anchor = [150.0, -90.0];
+        let under = camera.canvas_to_world(anchor);
+        camera.zoom_about(1.7, anchor);
+        assert_eq!(camera.zoom(), 2.5 * 1.7);
+        assert!(close(camera.canvas_to_world(anchor), under));
+    }
+
+    #[test]
+    fn panning_follows_the_cursor() {
+        let mut camera = camera();
+        let (from, to) = ([10.0, 20.0], [70.0, -40.0]);
+        let grabbed = camera.canvas_to_world(from);
+        camera.pan(from, to);
+        assert!(close(camera.canvas_to_world(to), grabbed));
+        // the world moves with the cursor, so the camera moves the other way,
+        // by the cursor's travel turned into the world and shrunk by the zoom
+        let travel = Transform::rotation(camera.rotation).apply([-60.0 / 2.5, 60.0 / 2.5]);
+        assert!(close(camera.position, [40.0 + travel[0], -25.0 + travel[1]]));
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::new();
+        camera.set_zoom(0.0);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+        camera.zoom_about(1e9, [30.0, 30.0]);
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+    }
+
+    #[test]
+    fn visible_region_bounds_the_turned_canvas() {
+        let mut camera = Camera::new();
+        camera.position = [100.0, 0.0];
+        camera.set_zoom(2.0);
+        camera.rotation = std::f32::consts::FRAC_PI_2;
+        let (min, max) = camera.visible_region([200.0, 100.0]);
+        // a quarter turn swaps the width and height
+        assert!(close(min, [75.0, -50.0]) && close(max, [125.0, 50.0]));
+    }
+
+}
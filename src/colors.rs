@@ -1,3 +1,6 @@
+use crate::entities::Vertex;
+use crate::transform::Transform;
+
 
 pub const BLACK: Color = Color { channels: [0, 0, 0, 255] };
 pub const WHITE: Color = Color { channels: [255, 255, 255,255] };
@@ -37,10 +40,35 @@ impl Color {
 		}
 	}
 
+	// hue in degrees, saturation and value between 0 and 1
+	pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Color {
+		let hue = hue.rem_euclid(360.0) / 60.0;
+		let chroma = value * saturation;
+		let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+		let (r, g, b) = match hue as u32 {
+			0 => (chroma, x, 0.0),
+			1 => (x, chroma, 0.0),
+			2 => (0.0, chroma, x),
+			3 => (0.0, x, chroma),
+			4 => (x, 0.0, chroma),
+			_ => (chroma, 0.0, x)
+		};
+		let m = value - chroma;
+		Self::new(
+			((r + m) * 255.0).round() as u32,
+			((g + m) * 255.0).round() as u32,
+			((b + m) * 255.0).round() as u32
+		)
+	}
+
 	pub fn as_slice(&self) -> &[u32; 4] {
 		&self.channels
 	}
 
+	pub fn as_rgba8(&self) -> [u8; 4] {
+		self.channels.map(|channel| channel as u8)
+	}
+
 	pub fn in_percentages(&self) -> [f32; 3] {
 		[
 			self.channels[0] as f32 / 255.0,
@@ -81,3 +109,54 @@ impl From<[f32; 3]> for Color {
 		)
 	}
 }
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Background {
+	Solid(Color),
+	VerticalGradient { top: Color, bottom: Color },
+	HorizontalGradient { left: Color, right: Color }
+}
+
+impl Background {
+
+	// the color the frame is cleared to before anything else is drawn
+	pub fn clear_color(&self) -> Color {
+		match self {
+			Self::Solid(color) => *color,
+			Self::VerticalGradient { bottom, .. } => *bottom,
+			Self::HorizontalGradient { left, .. } => *left
+		}
+	}
+
+	// a world-space quad that `view_projection` stretches over the whole frame,
+	// drawn right after the clear; solid backgrounds need nothing beyond the clear
+	pub fn vertices(&self, view_projection: &Transform) -> Vec<Vertex> {
+		let [top_left, bottom_left, bottom_right, top_right] = match self {
+			Self::Solid(_) => return vec![],
+			Self::VerticalGradient { top, bottom } => [*top, *bottom, *bottom, *top],
+			Self::HorizontalGradient { left, right } => [*left, *left, *right, *right]
+		};
+		let unproject = view_projection.inverse();
+		let corner = |x: f32, y: f32, color: Color| {
+			let [x, y] = unproject.apply([x, y]);
+			Vertex::new(x, y, 0.0, color)
+		};
+		let corners = [
+			corner(-1.0, 1.0, top_left),
+			corner(-1.0, -1.0, bottom_left),
+			corner(1.0, -1.0, bottom_right),
+			corner(1.0, 1.0, top_right)
+		];
+		vec![
+			corners[0], corners[1], corners[2],
+			corners[2], corners[3], corners[0]
+		]
+	}
+
+}
+
+impl Default for Background {
+	fn default() -> Self {
+		Self::Solid(BLACK)
+	}
+}
@@ -0,0 +1,184 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+// hebrides' Complex keeps its parts private, which rules it out for anything
+// that has to read them back, like plotting roots on the complex plane
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64
+}
+
+impl Complex {
+
+    pub const ZERO: Complex = Complex { re: 0.0, im: 0.0 };
+    pub const ONE: Complex = Complex { re: 1.0, im: 0.0 };
+    pub const I: Complex = Complex { re: 0.0, im: 1.0 };
+
+    pub fn new(re: f64, im: f64) -> Complex {
+        Self { re, im }
+    }
+
+    pub fn from_polar(modulus: f64, argument: f64) -> Complex {
+        let (sin, cos) = argument.sin_cos();
+        Self::new(modulus * cos, modulus * sin)
+    }
+
+    pub fn norm(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    pub fn norm_squared(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // principal argument in (-pi, pi]
+    pub fn arg(&self) -> f64 {
+        self.im.atan2(self.re)
+    }
+
+    pub fn conjugate(&self) -> Complex {
+        Self::new(self.re, -self.im)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+    pub fn exp(&self) -> Complex {
+        Self::from_polar(self.re.exp(), self.im)
+    }
+
+    // principal branch
+    pub fn ln(&self) -> Complex {
+        Self::new(self.norm().ln(), self.arg())
+    }
+
+    pub fn sqrt(&self) -> Complex {
+        Self::from_polar(self.norm().sqrt(), self.arg() / 2.0)
+    }
+
+    pub fn powc(&self, exponent: Complex) -> Complex {
+        if *self == Self::ZERO {
+            return if exponent == Self::ZERO { Self::ONE } else { Self::ZERO };
+        }
+        (self.ln() * exponent).exp()
+    }
+
+    pub fn powi(&self, exponent: i32) -> Complex {
+        let mut result = Self::ONE;
+        let mut base = if exponent < 0 { Self::ONE / *self } else { *self };
+        let mut remaining = exponent.unsigned_abs();
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = result * base;
+            }
+            base = base * base;
+            remaining >>= 1;
+        }
+        result
+    }
+
+    pub fn sin(&self) -> Complex {
+        Self::new(self.re.sin() * self.im.cosh(), self.re.cos() * self.im.sinh())
+    }
+
+    pub fn cos(&self) -> Complex {
+        Self::new(self.re.cos() * self.im.cosh(), -self.re.sin() * self.im.sinh())
+    }
+
+    pub fn tan(&self) -> Complex {
+        self.sin() / self.cos()
+    }
+
+    pub fn sinh(&self) -> Complex {
+        Self::new(self.re.sinh() * self.im.cos(), self.re.cosh() * self.im.sin())
+    }
+
+    pub fn cosh(&self) -> Complex {
+        Self::new(self.re.cosh() * self.im.cos(), self.re.sinh() * self.im.sin())
+    }
+
+    pub fn tanh(&self) -> Complex {
+        self.sinh() / self.cosh()
+    }
+
+    // -i ln(iz + sqrt(1 - z^2))
+    pub fn asin(&self) -> Complex {
+        -Self::I * (Self::I * *self + (Self::ONE - *self * *self).sqrt()).ln()
+    }
+
+    // pi/2 - asin(z)
+    pub fn acos(&self) -> Complex {
+        Self::from(std::f64::consts::FRAC_PI_2) - self.asin()
+    }
+
+    // i/2 ln((i + z) / (i - z))
+    pub fn atan(&self) -> Complex {
+        Self::I / Self::from(2.0) * ((Self::I + *self) / (Self::I - *self)).ln()
+    }
+
+}
+
+impl From<f64> for Complex {
+    fn from(value: f64) -> Self {
+        Self::new(value, 0.0)
+    }
+}
+
+impl std::fmt::Display for Complex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // honours a precision, so {:.2} rounds both parts
+        let precision = f.precision().unwrap_or(usize::MAX);
+        let part = |value: f64| match precision {
+            usize::MAX => format!("{}", value),
+            digits => format!("{:.*}", digits, value)
+        };
+        if self.im < 0.0 {
+            write!(f, "{} - {}i", part(self.re), part(-self.im))
+        } else {
+            write!(f, "{} + {}i", part(self.re), part(self.im))
+        }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, other: Complex) -> Complex {
+        Complex::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, other: Complex) -> Complex {
+        Complex::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, other: Complex) -> Complex {
+        Complex::new(
+            self.re * other.re - self.im * other.im,
+            self.re * other.im + self.im * other.re
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, other: Complex) -> Complex {
+        let denominator = other.norm_squared();
+        Complex::new(
+            (self.re * other.re + self.im * other.im) / denominator,
+            (self.im * other.re - self.re * other.im) / denominator
+        )
+    }
+}
+
+impl Neg for Complex {
+    type Output = Complex;
+    fn neg(self) -> Complex {
+        Complex::new(-self.re, -self.im)
+    }
+}
@@ -1,15 +1,20 @@
 
-use wgpu::util::DeviceExt;
 use hebrides::linal::Vector;
 
 use crate::colors::{Color, BLACK};
+use crate::pipeline::{Blend, PipelineKey, ShaderId, TextureId, Topology};
+use crate::polygon;
+use crate::stroke::Stroke;
+use crate::transform::Transform;
 
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct Vertex {
 	pub position: [f32; 3],
-	pub color: [f32; 3]
+	pub color: [f32; 3],
+	// in texels of the entity's texture, if it has one
+	pub texel: [f32; 2]
 }
 
 impl Vertex {
@@ -17,10 +22,16 @@ impl Vertex {
     pub fn new(x: f32, y: f32, z: f32, color: Color) -> Vertex {
         Self {
             position: [x, y, z],
-            color: color.in_percentages()
+            color: color.in_percentages(),
+            texel: [0.0, 0.0]
         }
     }
 
+    pub fn with_texel(mut self, u: f32, v: f32) -> Vertex {
+        self.texel = [u, v];
+        self
+    }
+
     pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
@@ -35,11 +46,25 @@ impl Vertex {
                     offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
                     shader_location: 1,
                     format: wgpu::VertexFormat::Float32x3
+                },
+                // after the `Instance` attributes
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 6]>() as wgpu::BufferAddress,
+                    shader_location: 5,
+                    format: wgpu::VertexFormat::Float32x2
                 }
             ]
         }
     }
 
+    pub fn transformed(&self, transform: &Transform) -> Vertex {
+        let [x, y] = transform.apply([self.position[0], self.position[1]]);
+        Vertex {
+            position: [x, y, self.position[2]],
+            ..*self
+        }
+    }
+
     pub fn as_vector(&self) -> Vector<f32> {
         Vector::new(self.position.to_vec())
     }
@@ -53,6 +78,9 @@ impl Vertex {
             }
             colors.push(point.color.into());
         }
+        for i in 0..3 {
+            neutral.position[i] /= points.len() as f32;
+        }
         neutral.color = Color::mix(colors.as_slice()).in_percentages();
         neutral
     }
@@ -71,134 +99,166 @@ impl From<Vertex> for Vector<f32> {
     }
 }
 
-pub struct SurfaceDimensions {
-    pub horizontal: f32,
-    pub vertical: f32
+// One copy of an instanced entity's vertices, scaled about the origin, then
+// moved by `offset` and tinted by `color`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    pub offset: [f32; 2],
+    pub scale: f32,
+    pub color: [f32; 3]
 }
 
-impl SurfaceDimensions {
+impl Instance {
 
-    pub fn new(horizontal: f32, vertical: f32) -> SurfaceDimensions {
-        Self { horizontal, vertical }
+    pub fn new(x: f32, y: f32, scale: f32, color: Color) -> Instance {
+        Self {
+            offset: [x, y],
+            scale,
+            color: color.in_percentages()
+        }
+    }
+
+    pub fn desc<'a>() -> wgpu::VertexBufferLayout<'a> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Instance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32
+                },
+                wgpu::VertexAttribute {
+                    offset: std::mem::size_of::<[f32; 3]>() as wgpu::BufferAddress,
+                    shader_location: 4,
+                    format: wgpu::VertexFormat::Float32x3
+                }
+            ]
+        }
+    }
+
+    // what instanced.wgsl does to every vertex of this copy
+    pub fn place(&self, vertex: &Vertex) -> Vertex {
+        let [x, y, z] = vertex.position;
+        Vertex {
+            position: [x * self.scale + self.offset[0], y * self.scale + self.offset[1], z],
+            color: [0, 1, 2].map(|i| vertex.color[i] * self.color[i]),
+            texel: vertex.texel
+        }
     }
 
 }
 
+// vertices are kept in world space, where one unit is one logical pixel at
+// the default view and the origin sits in the middle of the window, once
+// `transform` has moved them there. Instanced entities are drawn once per
+// instance, with their transformed vertices placed by it. Textured entities
+// sample `texture` at their vertices' texels.
 pub struct Entity {
     pub vertices: Vec<Vertex>,
-    vertex_buffer: wgpu::Buffer,
-    render_pipeline: wgpu::RenderPipeline,
-    pub surface_dimensions: SurfaceDimensions
+    pub pipeline: PipelineKey,
+    pub instances: Option<Vec<Instance>>,
+    pub transform: Transform,
+    pub texture: Option<TextureId>
 }
 
 impl Entity {
 
-    pub fn new(gpu: &wgpu::Device, surface_configuration: &wgpu::SurfaceConfiguration, width: f32, height: f32, vertices: Vec<Vertex>) -> Entity {
-        
-        let points = Self::normalize_coordinates(&vertices, width, height);
-
-        let vertex_buffer = gpu.create_buffer_init(
-            &wgpu::util::BufferInitDescriptor {
-                label: Some("Vertex Buffer"),
-                contents: bytemuck::cast_slice(points.as_slice()),
-                usage: wgpu::BufferUsages::VERTEX
-            }
-        );
-
-        let shader = gpu.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("shader.wgsl").into())
-        });
-
-        let render_pipeline_layout = gpu.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
-            label: Some("Render Pipeline Layout"),
-            bind_group_layouts: &[],
-            push_constant_ranges: &[]
-        });
-
-        let render_pipeline = gpu.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
-            label: Some("Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vertex_shader_main",
-                buffers: &[Vertex::desc()]
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fragment_shader_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_configuration.format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL
-                })]
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Ccw,
-                cull_mode: Some(wgpu::Face::Back),
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false
-            },
-            multiview: None
-        });
-
-        let surface_dimensions = SurfaceDimensions::new(width, height);
+    pub fn new(vertices: Vec<Vertex>, pipeline: PipelineKey) -> Entity {
+        Self { vertices, pipeline, instances: None, transform: Transform::IDENTITY, texture: None }
+    }
 
-        Self { 
-            vertices: points, 
-            vertex_buffer, 
-            render_pipeline,
-            surface_dimensions
-        }
+    pub fn num_vertices(&self) -> u32 {
+        self.vertices.len() as u32
     }
 
-    fn normalize_coordinates(vertices: &[Vertex], width: f32, height: f32) -> Vec<Vertex> {
-        let mut normalized = Vec::with_capacity(vertices.len());
-        for vertex in vertices {
-            normalized.push(Vertex::new(
-                vertex.position[0] / width,
-                vertex.position[1] / height,
-                vertex.position[2],
-                vertex.color.into()
-            ));
+    // whether `point` lands on one of the triangles as they are drawn, in the
+    // space the entity is drawn into
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let probe = Vertex::new(point[0], point[1], 0.0, BLACK);
+        let covers = |vertices: &[Vertex]| match self.pipeline.topology {
+            Topology::TriangleList => vertices.chunks_exact(3).any(|t| polygon::triangle_contains(&probe, &t[0], &t[1], &t[2])),
+            Topology::TriangleStrip => vertices.windows(3).any(|t| polygon::triangle_contains(&probe, &t[0], &t[1], &t[2]))
+        };
+        let vertices: Vec<Vertex> = self.vertices.iter().map(|vertex| vertex.transformed(&self.transform)).collect();
+        match &self.instances {
+            Some(instances) => instances.iter().any(|instance| {
+                covers(&vertices.iter().map(|vertex| instance.place(vertex)).collect::<Vec<_>>())
+            }),
+            None => covers(&vertices)
         }
-        normalized
     }
 
-    pub fn vertices(&self) -> &wgpu::Buffer {
-        &self.vertex_buffer
+}
+
+pub struct EntityBuilder {
+    vertices: Vec<Vertex>,
+    pipeline: PipelineKey,
+    instances: Option<Vec<Instance>>,
+    transform: Transform,
+    texture: Option<TextureId>
+}
+
+impl EntityBuilder {
+
+    fn new(vertices: Vec<Vertex>) -> EntityBuilder {
+        Self { vertices, pipeline: PipelineKey::default(), instances: None, transform: Transform::IDENTITY, texture: None }
     }
 
-    pub fn pipeline(&self) -> &wgpu::RenderPipeline {
-        &self.render_pipeline
+    // triangles given directly, three vertices each
+    pub fn from_triangles(vertices: Vec<Vertex>) -> EntityBuilder {
+        Self::new(vertices)
     }
 
-    pub fn num_vertices(&self) -> u32 {
-        self.vertices.len() as u32
+    pub fn transform(mut self, transform: Transform) -> EntityBuilder {
+        self.transform = transform;
+        self
     }
 
-}
+    // draws the shape once per instance in a single call, through
+    // instanced.wgsl unless another shader is picked
+    pub fn instanced(mut self, instances: Vec<Instance>) -> EntityBuilder {
+        if self.pipeline.shader == ShaderId::DEFAULT {
+            self.pipeline.shader = ShaderId::INSTANCED;
+        }
+        self.pipeline.instanced = true;
+        self.instances = Some(instances);
+        self
+    }
 
-pub struct EntityBuilder {
-    vertices: Vec<Vertex>
-}
+    // tints `texture` by the vertex colors through textured.wgsl, unless another
+    // shader is picked, blending by the texture's alpha
+    pub fn textured(mut self, texture: TextureId) -> EntityBuilder {
+        if self.pipeline.shader == ShaderId::DEFAULT {
+            self.pipeline.shader = ShaderId::TEXTURED;
+        }
+        self.pipeline.textured = true;
+        self.pipeline.blend = Blend::Alpha;
+        self.texture = Some(texture);
+        self
+    }
 
-impl EntityBuilder {
+    pub fn shader(mut self, shader: ShaderId) -> EntityBuilder {
+        self.pipeline.shader = shader;
+        self
+    }
 
-    fn new(vertices: Vec<Vertex>) -> EntityBuilder {
-        Self { vertices }
+    pub fn blend(mut self, blend: Blend) -> EntityBuilder {
+        self.pipeline.blend = blend;
+        self
     }
 
     fn valid_vertex_number(kind: &ShapeKind, num_vertices: usize) -> Option<ShapeError> {
+        if let ShapeKind::Polygon = kind {
+            // any outline long enough is fine; polygon::triangulate judges its shape
+            return None;
+        }
         match num_vertices.cmp(&kind.requisite_points()) {
             std::cmp::Ordering::Less => Some(ShapeError::VertexUnderspecification(*kind)),
             std::cmp::Ordering::Greater => Some(ShapeError::VertexOverspecification(*kind)),
@@ -211,7 +271,9 @@ impl EntityBuilder {
             return Err(err);
         }
         let points = match kind {
+            ShapeKind::Triangle if polygon::collinear(&vertices) => return Err(ShapeError::Degenerate),
             ShapeKind::Triangle => vertices,
+            ShapeKind::Polygon => polygon::triangulate(&vertices)?,
             ShapeKind::Rectangle => {
                 vec![
                     vertices[0], vertices[1], vertices[2],
@@ -257,8 +319,19 @@ impl EntityBuilder {
         Ok(EntityBuilder::new(points))
     }
 
-    pub fn build(self, gpu: &wgpu::Device, config: &wgpu::SurfaceConfiguration, width: u32, height: u32) -> Entity {
-        Entity::new(gpu, config, width as f32, height as f32, self.vertices)
+    // `zoom` as in `Stroke::tessellate`
+    pub fn from_stroke(points: Vec<Vertex>, stroke: &Stroke, zoom: f32) -> Result<EntityBuilder, ShapeError> {
+        Ok(EntityBuilder::new(stroke.tessellate(&points, zoom)?))
+    }
+
+    pub fn build(self) -> Entity {
+        Entity {
+            vertices: self.vertices,
+            pipeline: self.pipeline,
+            instances: self.instances,
+            transform: self.transform,
+            texture: self.texture
+        }
     }
 
 }
@@ -267,7 +340,9 @@ impl EntityBuilder {
 pub enum ShapeKind {
     Triangle,
     Rectangle,
-    Circle(f32)
+    Circle(f32),
+    // a simple outline of three or more vertices, in either winding
+    Polygon
 }
 
 impl ShapeKind {
@@ -276,7 +351,8 @@ impl ShapeKind {
         match self {
             Self::Triangle => 3,
             Self::Rectangle => 4,
-            Self::Circle(_) => 1
+            Self::Circle(_) => 1,
+            Self::Polygon => 3
         }
     }
 
@@ -285,21 +361,31 @@ impl ShapeKind {
 #[derive(Debug)]
 pub enum ShapeError {
     VertexOverspecification(ShapeKind),
-    VertexUnderspecification(ShapeKind)
-}   
+    VertexUnderspecification(ShapeKind),
+    TooFewPoints(usize),
+    SelfIntersecting,
+    Degenerate,
+    NotConvex
+}
 
 impl std::fmt::Display for ShapeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let msg = match self {
+            Self::TooFewPoints(count) => return write!(f, "Too few vertices to outline the shape, got {}", count),
+            Self::SelfIntersecting => "A polygon outline must not cross or touch itself",
+            Self::Degenerate => "The shape would not cover any area",
+            Self::NotConvex => "A rigid body outline must be convex",
             Self::VertexOverspecification(shape_kind) => match shape_kind {
                 ShapeKind::Triangle => "A triangle requires only three vertices",
                 ShapeKind::Rectangle => "A rectangle requires only four vertices",
-                ShapeKind::Circle(_) => "A circle requires only one vertex for its center"
+                ShapeKind::Circle(_) => "A circle requires only one vertex for its center",
+                ShapeKind::Polygon => "A polygon accepts any number of vertices"
             },
             Self::VertexUnderspecification(shape_kind) => match shape_kind {
                 ShapeKind::Triangle => "A triangle requires at least three vertices",
                 ShapeKind::Rectangle => "A rectangle requires at least four vertices",
-                ShapeKind::Circle(_) => "A circle requires a vertex for its center"
+                ShapeKind::Circle(_) => "A circle requires a vertex for its center",
+                ShapeKind::Polygon => "A polygon requires at least three vertices"
             }
         };
         write!(f, "{}", msg)
@@ -0,0 +1,780 @@
+use std::collections::{BTreeSet, HashMap};
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
+use crate::complex::Complex;
+
+// byte offsets into the source text, end exclusive
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+impl Span {
+
+    fn new(start: usize, end: usize) -> Span {
+        Self { start, end }
+    }
+
+    fn to(&self, other: Span) -> Span {
+        Self::new(self.start, other.end)
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Number(f64),
+    Identifier(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Caret,
+    LeftParen,
+    RightParen,
+    Comma,
+    End
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    span: Span
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ParseError> {
+
+    let mut tokens = vec![];
+    let mut characters = source.char_indices().peekable();
+
+    while let Some(&(start, character)) = characters.peek() {
+
+        if character.is_whitespace() {
+            characters.next();
+            continue;
+        }
+
+        if character.is_ascii_digit() || character == '.' {
+            let mut end = start;
+            let mut previous = ' ';
+            while let Some(&(index, next)) = characters.peek() {
+                // an exponent only counts when digits follow, so `2e` stays 2 * e
+                let exponent = (next == 'e' || next == 'E') && {
+                    let rest = &source[index + 1..];
+                    let rest = rest.strip_prefix(['+', '-']).unwrap_or(rest);
+                    rest.starts_with(|c: char| c.is_ascii_digit())
+                };
+                let signed = (next == '+' || next == '-') && (previous == 'e' || previous == 'E');
+                if !(next.is_ascii_digit() || next == '.' || exponent || signed) {
+                    break;
+                }
+                previous = next;
+                end = index + next.len_utf8();
+                characters.next();
+            }
+            let span = Span::new(start, end);
+            let value = source[start..end].parse().map_err(|_| ParseError::new(ParseErrorKind::InvalidNumber, span))?;
+            tokens.push(Token { kind: TokenKind::Number(value), span });
+            continue;
+        }
+
+        if character.is_alphabetic() || character == '_' {
+            let mut end = start;
+            while let Some(&(index, next)) = characters.peek() {
+                if !(next.is_alphanumeric() || next == '_') {
+                    break;
+                }
+                end = index + next.len_utf8();
+                characters.next();
+            }
+            tokens.push(Token {
+                kind: TokenKind::Identifier(source[start..end].to_string()),
+                span: Span::new(start, end)
+            });
+            continue;
+        }
+
+        let kind = match character {
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '*' => TokenKind::Star,
+            '/' => TokenKind::Slash,
+            '^' => TokenKind::Caret,
+            '(' => TokenKind::LeftParen,
+            ')' => TokenKind::RightParen,
+            ',' => TokenKind::Comma,
+            _ => return Err(ParseError::new(
+                ParseErrorKind::UnexpectedCharacter(character),
+                Span::new(start, start + character.len_utf8())
+            ))
+        };
+        tokens.push(Token { kind, span: Span::new(start, start + character.len_utf8()) });
+        characters.next();
+    }
+
+    tokens.push(Token { kind: TokenKind::End, span: Span::new(source.len(), source.len()) });
+    Ok(tokens)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power
+}
+
+impl BinaryOperator {
+
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Self::Add | Self::Subtract => 1,
+            Self::Multiply | Self::Divide => 2,
+            Self::Power => 4
+        }
+    }
+
+    pub fn right_associative(&self) -> bool {
+        *self == Self::Power
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Add => "+",
+            Self::Subtract => "-",
+            Self::Multiply => "*",
+            Self::Divide => "/",
+            Self::Power => "^"
+        }
+    }
+
+}
+
+// binds tighter than * and / but looser than ^, so -x^2 is -(x^2)
+const NEGATION_PRECEDENCE: u8 = 3;
+// subexpressions nested deeper than this are rejected rather than overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Constant {
+    Pi,
+    E,
+    I
+}
+
+impl Constant {
+
+    pub fn from_name(name: &str) -> Option<Constant> {
+        match name {
+            "pi" | "π" => Some(Self::Pi),
+            "e" => Some(Self::E),
+            "i" => Some(Self::I),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pi => "pi",
+            Self::E => "e",
+            Self::I => "i"
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Sinh,
+    Cosh,
+    Tanh,
+    Exp,
+    Ln,
+    Log,
+    Sqrt,
+    Abs
+}
+
+impl Function {
+
+    pub fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "sin" => Some(Self::Sin),
+            "cos" => Some(Self::Cos),
+            "tan" => Some(Self::Tan),
+            "asin" => Some(Self::Asin),
+            "acos" => Some(Self::Acos),
+            "atan" => Some(Self::Atan),
+            "sinh" => Some(Self::Sinh),
+            "cosh" => Some(Self::Cosh),
+            "tanh" => Some(Self::Tanh),
+            "exp" => Some(Self::Exp),
+            "ln" => Some(Self::Ln),
+            "log" => Some(Self::Log),
+            "sqrt" => Some(Self::Sqrt),
+            "abs" => Some(Self::Abs),
+            _ => None
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Sin => "sin",
+            Self::Cos => "cos",
+            Self::Tan => "tan",
+            Self::Asin => "asin",
+            Self::Acos => "acos",
+            Self::Atan => "atan",
+            Self::Sinh => "sinh",
+            Self::Cosh => "cosh",
+            Self::Tanh => "tanh",
+            Self::Exp => "exp",
+            Self::Ln => "ln",
+            Self::Log => "log",
+            Self::Sqrt => "sqrt",
+            Self::Abs => "abs"
+        }
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Number(f64),
+    Constant(Constant),
+    Variable(String),
+    Negate(Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Call(Function, Box<Expression>)
+}
+
+impl Expression {
+
+    // sin(x) * x^2 - 3, 2x(x + 1), e^(i pi), ...
+    pub fn parse(source: &str) -> Result<Expression, ParseError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { source, tokens: &tokens, position: 0, depth: 0 };
+        let expression = parser.expression(0)?;
+        match parser.peek().kind {
+            TokenKind::End => Ok(expression),
+            _ => Err(parser.unexpected())
+        }
+    }
+
+    pub fn evaluate<T: Scalar>(&self, variables: &HashMap<String, T>) -> Result<T, EvaluationError> {
+        self.evaluate_with(&|name| variables.get(name).copied())
+    }
+
+    pub fn evaluate_with<T: Scalar>(&self, lookup: &dyn Fn(&str) -> Option<T>) -> Result<T, EvaluationError> {
+        Ok(match self {
+            Self::Number(value) => T::from_f64(*value),
+            Self::Constant(constant) => T::constant(*constant).ok_or(EvaluationError::NotReal(*constant))?,
+            Self::Variable(name) => lookup(name).ok_or_else(|| EvaluationError::UnboundVariable(name.clone()))?,
+            Self::Negate(operand) => -operand.evaluate_with(lookup)?,
+            Self::Binary(operator, left, right) => {
+                let (left, right) = (left.evaluate_with(lookup)?, right.evaluate_with(lookup)?);
+                match operator {
+                    BinaryOperator::Add => left + right,
+                    BinaryOperator::Subtract => left - right,
+                    BinaryOperator::Multiply => left * right,
+                    BinaryOperator::Divide => left / right,
+                    BinaryOperator::Power => left.pow(right)
+                }
+            },
+            Self::Call(function, argument) => T::apply(*function, argument.evaluate_with(lookup)?)
+        })
+    }
+
+    // names of every variable the expression refers to
+    pub fn variables(&self) -> BTreeSet<String> {
+        let mut variables = BTreeSet::new();
+        self.visit(&mut |expression| if let Self::Variable(name) = expression {
+            variables.insert(name.clone());
+        });
+        variables
+    }
+
+    fn visit(&self, visitor: &mut dyn FnMut(&Expression)) {
+        visitor(self);
+        match self {
+            Self::Negate(operand) | Self::Call(_, operand) => operand.visit(visitor),
+            Self::Binary(_, left, right) => {
+                left.visit(visitor);
+                right.visit(visitor);
+            },
+            _ => {}
+        }
+    }
+
+    fn single_variable(&self, variable: &str) -> Result<(), EvaluationError> {
+        match self.variables().into_iter().find(|name| name != variable) {
+            Some(unbound) => Err(EvaluationError::UnboundVariable(unbound)),
+            None => Ok(())
+        }
+    }
+
+    // a real function of `variable`, e.g. for the grapher; points where the
+    // expression is undefined come out as NaN
+    pub fn real_function(&self, variable: &str) -> Result<impl Fn(f64) -> f64 + 'static, EvaluationError> {
+        self.single_variable(variable)?;
+        let mut imaginary = false;
+        self.visit(&mut |expression| imaginary |= *expression == Self::Constant(Constant::I));
+        if imaginary {
+            return Err(EvaluationError::NotReal(Constant::I));
+        }
+        let (expression, variable) = (self.clone(), variable.to_string());
+        Ok(move |x| expression.evaluate_with(&|name| (name == variable).then_some(x)).unwrap_or(f64::NAN))
+    }
+
+    pub fn complex_function(&self, variable: &str) -> Result<impl Fn(Complex) -> Complex + 'static, EvaluationError> {
+        self.single_variable(variable)?;
+        let (expression, variable) = (self.clone(), variable.to_string());
+        Ok(move |z| expression.evaluate_with(&|name| (name == variable).then_some(z)).unwrap_or(Complex::new(f64::NAN, f64::NAN)))
+    }
+
+}
+
+// binding strength when printed, with atoms and calls binding tightest
+fn precedence(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Number(value) if *value < 0.0 => NEGATION_PRECEDENCE,
+        Expression::Negate(_) => NEGATION_PRECEDENCE,
+        Expression::Binary(operator, _, _) => operator.precedence(),
+        _ => 5
+    }
+}
+
+// prints with as few parentheses as reparsing to the same tree allows
+impl std::fmt::Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let grouped = |f: &mut std::fmt::Formatter<'_>, expression: &Expression, parenthesize: bool| {
+            if parenthesize {
+                write!(f, "({})", expression)
+            } else {
+                write!(f, "{}", expression)
+            }
+        };
+        match self {
+            Self::Number(value) => write!(f, "{}", value),
+            Self::Constant(constant) => write!(f, "{}", constant.name()),
+            Self::Variable(name) => write!(f, "{}", name),
+            Self::Negate(operand) => {
+                write!(f, "-")?;
+                grouped(f, operand, precedence(operand) < NEGATION_PRECEDENCE)
+            },
+            Self::Call(function, argument) => write!(f, "{}({})", function.name(), argument),
+            Self::Binary(operator, left, right) => {
+                let own = operator.precedence();
+                let (left_precedence, right_precedence) = (precedence(left), precedence(right));
+                grouped(f, left, left_precedence < own || (left_precedence == own && operator.right_associative()))?;
+                match operator {
+                    BinaryOperator::Power => write!(f, "^")?,
+                    _ => write!(f, " {} ", operator.symbol())?
+                }
+                grouped(f, right, right_precedence < own || (right_precedence == own && !operator.right_associative()))
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for Expression {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+// Precedence climbing over the token stream. A factor directly followed by a
+// name or an opening parenthesis is multiplied by it, so 2x and 3(x + 1) work.
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    position: usize,
+    depth: usize
+}
+
+impl<'a> Parser<'a> {
+
+    fn peek(&self) -> &'a Token {
+        &self.tokens[self.position]
+    }
+
+    fn advance(&mut self) -> &'a Token {
+        let token = &self.tokens[self.position];
+        if token.kind != TokenKind::End {
+            self.position += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self) -> ParseError {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::End => ParseError::new(ParseErrorKind::UnexpectedEnd, token.span),
+            _ => ParseError::new(
+                ParseErrorKind::UnexpectedToken(self.source[token.span.start..token.span.end].to_string()),
+                token.span
+            )
+        }
+    }
+
+    fn expression(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ParseError::new(ParseErrorKind::TooDeep, self.peek().span));
+        }
+        self.depth += 1;
+        let expression = self.operations(min_precedence);
+        self.depth -= 1;
+        expression
+    }
+
+    fn operations(&mut self, min_precedence: u8) -> Result<Expression, ParseError> {
+
+        let mut left = self.unary()?;
+
+        loop {
+            let (operator, implicit) = match self.peek().kind {
+                TokenKind::Plus => (BinaryOperator::Add, false),
+                TokenKind::Minus => (BinaryOperator::Subtract, false),
+                TokenKind::Star => (BinaryOperator::Multiply, false),
+                TokenKind::Slash => (BinaryOperator::Divide, false),
+                TokenKind::Caret => (BinaryOperator::Power, false),
+                TokenKind::Identifier(_) | TokenKind::LeftParen => (BinaryOperator::Multiply, true),
+                _ => break
+            };
+            let precedence = operator.precedence();
+            if precedence < min_precedence {
+                break;
+            }
+            if !implicit {
+                self.advance();
+            }
+            let next_precedence = if operator.right_associative() { precedence } else { precedence + 1 };
+            let right = self.expression(next_precedence)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, ParseError> {
+        match self.peek().kind {
+            TokenKind::Minus => {
+                self.advance();
+                Ok(Expression::Negate(Box::new(self.expression(NEGATION_PRECEDENCE)?)))
+            },
+            TokenKind::Plus => {
+                self.advance();
+                self.expression(NEGATION_PRECEDENCE)
+            },
+            _ => self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression, ParseError> {
+        let token = self.peek();
+        match &token.kind {
+            TokenKind::Number(value) => {
+                self.advance();
+                Ok(Expression::Number(*value))
+            },
+            TokenKind::Identifier(name) => {
+                self.advance();
+                if let Some(function) = Function::from_name(name) {
+                    return self.call(function, token.span);
+                }
+                Ok(match Constant::from_name(name) {
+                    Some(constant) => Expression::Constant(constant),
+                    None => Expression::Variable(name.clone())
+                })
+            },
+            TokenKind::LeftParen => {
+                self.advance();
+                let inner = self.expression(0)?;
+                if self.peek().kind != TokenKind::RightParen {
+                    return Err(match self.peek().kind {
+                        TokenKind::End => ParseError::new(ParseErrorKind::UnclosedParenthesis, token.span),
+                        _ => self.unexpected()
+                    });
+                }
+                self.advance();
+                Ok(inner)
+            },
+            _ => Err(self.unexpected())
+        }
+    }
+
+    fn call(&mut self, function: Function, name: Span) -> Result<Expression, ParseError> {
+
+        if self.peek().kind != TokenKind::LeftParen {
+            return Err(ParseError::new(ParseErrorKind::MissingArguments(function), name));
+        }
+        let open = self.advance().span;
+
+        let mut arguments = vec![self.expression(0)?];
+        while self.peek().kind == TokenKind::Comma {
+            self.advance();
+            arguments.push(self.expression(0)?);
+        }
+
+        match self.peek().kind {
+            TokenKind::RightParen => {},
+            TokenKind::End => return Err(ParseError::new(ParseErrorKind::UnclosedParenthesis, open)),
+            _ => return Err(self.unexpected())
+        }
+        let close = self.advance().span;
+
+        if arguments.len() != 1 {
+            return Err(ParseError::new(
+                ParseErrorKind::WrongArgumentCount { function, found: arguments.len() },
+                name.to(close)
+            ));
+        }
+        Ok(Expression::Call(function, Box::new(arguments.remove(0))))
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseErrorKind {
+    UnexpectedCharacter(char),
+    InvalidNumber,
+    UnexpectedToken(String),
+    UnexpectedEnd,
+    UnclosedParenthesis,
+    MissingArguments(Function),
+    WrongArgumentCount { function: Function, found: usize },
+    TooDeep
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span
+}
+
+impl ParseError {
+
+    fn new(kind: ParseErrorKind, span: Span) -> ParseError {
+        Self { kind, span }
+    }
+
+    // the source with the offending part marked underneath, for showing to users
+    pub fn underline(&self, source: &str) -> String {
+        let offset = source[..self.span.start].chars().count();
+        let width = source[self.span.start..self.span.end].chars().count().max(1);
+        format!("{}\n{}{}", source, " ".repeat(offset), "^".repeat(width))
+    }
+
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ParseErrorKind::UnexpectedCharacter(character) => write!(f, "Unexpected character '{}'", character)?,
+            ParseErrorKind::InvalidNumber => write!(f, "Malformed number")?,
+            ParseErrorKind::UnexpectedToken(token) => write!(f, "Unexpected '{}'", token)?,
+            ParseErrorKind::UnexpectedEnd => write!(f, "Expression ends unexpectedly")?,
+            ParseErrorKind::UnclosedParenthesis => write!(f, "Parenthesis is never closed")?,
+            ParseErrorKind::MissingArguments(function) => write!(f, "{} must be called like {}(x)", function.name(), function.name())?,
+            ParseErrorKind::WrongArgumentCount { function, found } => write!(f, "{} takes one argument, not {}", function.name(), found)?,
+            ParseErrorKind::TooDeep => write!(f, "Expression is nested too deeply")?
+        }
+        write!(f, " at {}..{}", self.span.start, self.span.end)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvaluationError {
+    UnboundVariable(String),
+    // the constant has no value among the reals
+    NotReal(Constant)
+}
+
+impl std::fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnboundVariable(name) => write!(f, "No value was given for {}", name),
+            Self::NotReal(constant) => write!(f, "{} is not a real number", constant.name())
+        }
+    }
+}
+
+// the numbers an expression can be evaluated over
+pub trait Scalar: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self> + Neg<Output = Self> {
+
+    fn from_f64(value: f64) -> Self;
+
+    fn constant(constant: Constant) -> Option<Self>;
+
+    fn pow(self, exponent: Self) -> Self;
+
+    fn apply(function: Function, argument: Self) -> Self;
+
+}
+
+impl Scalar for f64 {
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn constant(constant: Constant) -> Option<Self> {
+        match constant {
+            Constant::Pi => Some(std::f64::consts::PI),
+            Constant::E => Some(std::f64::consts::E),
+            Constant::I => None
+        }
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        self.powf(exponent)
+    }
+
+    fn apply(function: Function, x: Self) -> Self {
+        match function {
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Asin => x.asin(),
+            Function::Acos => x.acos(),
+            Function::Atan => x.atan(),
+            Function::Sinh => x.sinh(),
+            Function::Cosh => x.cosh(),
+            Function::Tanh => x.tanh(),
+            Function::Exp => x.exp(),
+            Function::Ln => x.ln(),
+            Function::Log => x.log10(),
+            Function::Sqrt => x.sqrt(),
+            Function::Abs => x.abs()
+        }
+    }
+
+}
+
+impl Scalar for Complex {
+
+    fn from_f64(value: f64) -> Self {
+        Complex::from(value)
+    }
+
+    fn constant(constant: Constant) -> Option<Self> {
+        match constant {
+            Constant::I => Some(Complex::I),
+            real => f64::constant(real).map(Complex::from)
+        }
+    }
+
+    fn pow(self, exponent: Self) -> Self {
+        // repeated multiplication keeps z^2 and friends exact
+        if exponent.im == 0.0 && exponent.re.fract() == 0.0 && exponent.re.abs() <= i32::MAX as f64 {
+            return self.powi(exponent.re as i32);
+        }
+        self.powc(exponent)
+    }
+
+    fn apply(function: Function, z: Self) -> Self {
+        match function {
+            Function::Sin => z.sin(),
+            Function::Cos => z.cos(),
+            Function::Tan => z.tan(),
+            Function::Asin => z.asin(),
+            Function::Acos => z.acos(),
+            Function::Atan => z.atan(),
+            Function::Sinh => z.sinh(),
+            Function::Cosh => z.cosh(),
+            Function::Tanh => z.tanh(),
+            Function::Exp => z.exp(),
+            Function::Ln => z.ln(),
+            Function::Log => z.ln() / Complex::from(std::f64::consts::LN_10),
+            Function::Sqrt => z.sqrt(),
+            Function::Abs => Complex::from(z.norm())
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn real(source: &str, x: f64) -> f64 {
+        Expression::parse(source).unwrap().real_function("x").unwrap()(x)
+    }
+
+    #[test]
+    fn operators_follow_precedence_and_associativity() {
+        assert_eq!(real("1 + 2 * 3 ^ 2", 0.0), 19.0);
+        assert_eq!(real("2 ^ 3 ^ 2", 0.0), 512.0);
+        assert_eq!(real("10 - 4 - 3", 0.0), 3.0);
+        assert_eq!(real("-x ^ 2", 3.0), -9.0);
+        assert_eq!(real("2 ^ -x", 1.0), 0.5);
+        assert_eq!(real("sin(x) * x^2 - 3", 0.0), -3.0);
+    }
+
+    #[test]
+    fn juxtaposition_multiplies() {
+        assert_eq!(real("2x(x + 1)", 3.0), 24.0);
+        assert_eq!(real("2 pi", 0.0), 2.0 * std::f64::consts::PI);
+        assert_eq!(real("1.5e2x", 1.0), 150.0);
+        assert_eq!(real("2e", 0.0), 2.0 * std::f64::consts::E);
+    }
+
+    #[test]
+    fn errors_point_at_their_source() {
+        let error = Expression::parse("sin(x, 2) + 1").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::WrongArgumentCount { function: Function::Sin, found: 2 });
+        assert_eq!(error.span, Span::new(0, 9));
+
+        let error = Expression::parse("3 * (x + 1").unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::UnclosedParenthesis);
+        assert_eq!(error.underline("3 * (x + 1"), "3 * (x + 1\n    ^");
+
+        assert_eq!(Expression::parse("x $ 2").unwrap_err().span, Span::new(2, 3));
+        assert_eq!(Expression::parse("x +").unwrap_err().kind, ParseErrorKind::UnexpectedEnd);
+        assert_eq!(Expression::parse("(x))").unwrap_err().kind, ParseErrorKind::UnexpectedToken(")".to_string()));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error() {
+        let source = format!("{}x{}", "(".repeat(300_000), ")".repeat(300_000));
+        let error = Expression::parse(&source).unwrap_err();
+        assert_eq!(error.kind, ParseErrorKind::TooDeep);
+        assert_eq!(error.span, Span::new(MAX_DEPTH, MAX_DEPTH + 1));
+        assert!(Expression::parse(&format!("{}x", "-".repeat(300_000))).is_err());
+        let nested = format!("{}x{}", "(".repeat(100), ")".repeat(100));
+        assert_eq!(real(&nested, 2.0), 2.0);
+    }
+
+    #[test]
+    fn variables_must_be_bound() {
+        let expression = Expression::parse("a x + b").unwrap();
+        let variables: Vec<_> = expression.variables().into_iter().collect();
+        assert_eq!(variables, ["a", "b", "x"]);
+        assert!(matches!(expression.real_function("x"), Err(EvaluationError::UnboundVariable(_))));
+
+        let bindings = HashMap::from([("a".to_string(), 2.0), ("b".to_string(), 1.0), ("x".to_string(), 4.0)]);
+        assert_eq!(expression.evaluate(&bindings), Ok(9.0));
+    }
+
+    #[test]
+    fn evaluates_over_complex_numbers() {
+        let unit = Expression::parse("e^(i pi)").unwrap().evaluate::<Complex>(&HashMap::new()).unwrap();
+        assert!((unit - Complex::new(-1.0, 0.0)).norm() < 1e-12);
+
+        let square = Expression::parse("z^2 + 1").unwrap().complex_function("z").unwrap();
+        assert_eq!(square(Complex::I), Complex::ZERO);
+
+        assert_eq!(Expression::parse("i").unwrap().evaluate::<f64>(&HashMap::new()), Err(EvaluationError::NotReal(Constant::I)));
+    }
+
+}
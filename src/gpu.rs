@@ -0,0 +1,562 @@
+use std::ops::Range;
+
+use winit::window::Window;
+
+use crate::entities::{Instance, Vertex};
+use crate::image::Image;
+use crate::pipeline::{PipelineCache, PipelineKey, ShaderId, TextureId, MAX_PARAMETERS};
+use crate::renderer::{Frame, RenderError, Renderer};
+use crate::transform::Transform;
+
+const OFFSCREEN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+// of every texture entities sample, matching `Image`
+const TEXTURE_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+// initial sizes of the shared vertex and instance buffers, grown on demand
+const INITIAL_VERTEX_BUFFER_SIZE: wgpu::BufferAddress = 1 << 16;
+const INITIAL_INSTANCE_BUFFER_SIZE: wgpu::BufferAddress = 1 << 12;
+
+// indices into `WgpuRenderer::globals`
+const WORLD_LAYER: usize = 0;
+const SCREEN_LAYER: usize = 1;
+
+// a contiguous run of the shared vertex buffer drawn with one pipeline and
+// texture through one layer's transform, once per instance in `instances`
+struct Batch {
+    pipeline: PipelineKey,
+    texture: Option<TextureId>,
+    layer: usize,
+    vertices: Range<u32>,
+    instances: Range<u32>
+}
+
+// a uniform buffer holding one view projection and the shader parameters, and
+// the bind group exposing it
+struct Globals {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup
+}
+
+// an uploaded image and the bind group exposing it to textured pipelines
+struct Texture {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup
+}
+
+pub struct WgpuRenderer {
+    // None => headless, frames only ever end up in offscreen textures
+    surface: Option<wgpu::Surface>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    pipelines: PipelineCache,
+    // one per layer, see WORLD_LAYER and SCREEN_LAYER
+    globals: [Globals; 2],
+    // every entity of a frame is streamed into this one buffer
+    vertex_buffer: wgpu::Buffer,
+    // and the instances of every instanced entity into this one
+    instance_buffer: wgpu::Buffer,
+    // shared by every texture, indexed by `TextureId`
+    sampler: wgpu::Sampler,
+    textures: Vec<Texture>
+}
+
+impl WgpuRenderer {
+
+    pub async fn new(window: &Window) -> WgpuRenderer {
+
+        let size = window.inner_size();
+
+        let instance = Self::create_instance();
+
+        let surface = unsafe { instance.create_surface(window) }.unwrap();
+
+        let adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                // HighPerformance => more performant device, but more power-hungry
+                power_preference: wgpu::PowerPreference::HighPerformance,
+                // Some(&surface) => matches `surface` to the GPU
+                compatible_surface: Some(&surface),
+                // false => forces rendering system to use the GPU and not a
+                //          fallback system of any kind
+                force_fallback_adapter: false
+            }
+        ).await.unwrap();
+
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        let surface_caps = surface.get_capabilities(&adapter);
+
+        let surface_format = surface_caps.formats.iter()
+                                                 .copied()
+                                                 .find(|f| !f.describe().srgb)
+                                                 .unwrap();
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: surface_caps.present_modes[0],
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![]
+        };
+
+        surface.configure(&device, &config);
+
+        let pipelines = PipelineCache::new(&device, config.format);
+        let globals = [Self::create_globals(&device, &pipelines), Self::create_globals(&device, &pipelines)];
+        let vertex_buffer = Self::create_vertex_buffer(&device, INITIAL_VERTEX_BUFFER_SIZE);
+        let instance_buffer = Self::create_vertex_buffer(&device, INITIAL_INSTANCE_BUFFER_SIZE);
+        let sampler = Self::create_sampler(&device);
+
+        Self {
+            surface: Some(surface),
+            device,
+            queue,
+            config,
+            pipelines,
+            globals,
+            vertex_buffer,
+            instance_buffer,
+            sampler,
+            textures: vec![]
+        }
+    }
+
+    pub async fn offscreen(width: u32, height: u32) -> Option<WgpuRenderer> {
+
+        let instance = Self::create_instance();
+
+        let mut options = wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            // None => there is no window to present to
+            compatible_surface: None,
+            force_fallback_adapter: false
+        };
+
+        let adapter = match instance.request_adapter(&options).await {
+            Some(adapter) => adapter,
+            None => {
+                // no GPU => settle for a software adapter if the platform has one
+                options.force_fallback_adapter = true;
+                instance.request_adapter(&options).await?
+            }
+        };
+
+        let (device, queue) = Self::request_device(&adapter).await;
+
+        // mirrors the configuration a window surface would have so that the
+        // offscreen path shares all of its sizing logic with the windowed one
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format: OFFSCREEN_FORMAT,
+            width,
+            height,
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+            view_formats: vec![]
+        };
+
+        let pipelines = PipelineCache::new(&device, config.format);
+        let globals = [Self::create_globals(&device, &pipelines), Self::create_globals(&device, &pipelines)];
+        let vertex_buffer = Self::create_vertex_buffer(&device, INITIAL_VERTEX_BUFFER_SIZE);
+        let instance_buffer = Self::create_vertex_buffer(&device, INITIAL_INSTANCE_BUFFER_SIZE);
+        let sampler = Self::create_sampler(&device);
+
+        Some(Self {
+            surface: None,
+            device,
+            queue,
+            config,
+            pipelines,
+            globals,
+            vertex_buffer,
+            instance_buffer,
+            sampler,
+            textures: vec![]
+        })
+    }
+
+    fn create_instance() -> wgpu::Instance {
+        wgpu::Instance::new(wgpu::InstanceDescriptor {
+            // wgpu::Backends::all() => Vulkan + Metal + DX12 + WebGPU
+            backends: wgpu::Backends::all(),
+            // default shader compiler => naga
+            dx12_shader_compiler: Default::default()
+        })
+    }
+
+    async fn request_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+        adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                // specifies extra features potentially available on the GPU
+                features: wgpu::Features::empty(),
+                // the general limits on the types of resources able to be requested
+                limits: wgpu::Limits::default(),
+                label: Some("Local GPU Device")
+            },
+            None
+        ).await.unwrap()
+    }
+
+    // copies every entity into the shared vertex buffer, merging neighbouring
+    // entities that use the same pipeline into a single draw call
+    fn upload(&mut self, frame: &Frame) -> Vec<Batch> {
+
+        let mut vertices: Vec<Vertex> = Vec::new();
+        let mut instances: Vec<Instance> = Vec::new();
+        let mut batches: Vec<Batch> = Vec::new();
+
+        let mut parameters = [[0.0f32; 4]; MAX_PARAMETERS];
+        for (slot, parameter) in parameters.iter_mut().zip(frame.parameters) {
+            *slot = *parameter;
+        }
+        for (layer, transform) in [(WORLD_LAYER, &frame.view_projection), (SCREEN_LAYER, &frame.screen_projection)] {
+            let columns = transform.columns();
+            let transform: &[u8] = bytemuck::cast_slice(&columns);
+            self.queue.write_buffer(&self.globals[layer].buffer, 0, transform);
+            // the parameters follow right after the view projection
+            self.queue.write_buffer(&self.globals[layer].buffer, transform.len() as wgpu::BufferAddress, bytemuck::cast_slice(&parameters));
+        }
+
+        let background = frame.background.vertices(&frame.screen_projection);
+        let runs = std::iter::once((SCREEN_LAYER, background.as_slice(), None))
+                            .chain(frame.entities.iter().map(|e| (WORLD_LAYER, e.vertices.as_slice(), Some(*e))))
+                            .chain(frame.overlay.iter().map(|e| (SCREEN_LAYER, e.vertices.as_slice(), Some(*e))));
+
+        for (layer, run, entity) in runs {
+
+            let pipeline = entity.map_or_else(PipelineKey::default, |e| e.pipeline);
+            let copies = entity.and_then(|e| e.instances.as_deref());
+            let transform = entity.map_or(Transform::IDENTITY, |e| e.transform);
+            let texture = entity.and_then(|e| e.texture).filter(|_| pipeline.textured);
+
+            if run.is_empty() || copies.is_some_and(|copies| copies.is_empty()) || (pipeline.textured && texture.is_none()) {
+                continue;
+            }
+
+            self.pipelines.prepare(&self.device, pipeline);
+
+            let start = vertices.len() as u32;
+            // vertices are streamed every frame anyway, so entity transforms
+            // are applied here rather than in the shaders
+            if transform == Transform::IDENTITY {
+                vertices.extend_from_slice(run);
+            } else {
+                vertices.extend(run.iter().map(|vertex| vertex.transformed(&transform)));
+            }
+            let end = vertices.len() as u32;
+
+            // every instanced entity is a draw call of its own
+            if let Some(copies) = copies {
+                let first = instances.len() as u32;
+                instances.extend_from_slice(copies);
+                batches.push(Batch { pipeline, texture, layer, vertices: start..end, instances: first..instances.len() as u32 });
+                continue;
+            }
+
+            match batches.last_mut() {
+                Some(batch) if batch.pipeline == pipeline && batch.texture == texture && batch.layer == layer && pipeline.topology.mergeable() => {
+                    batch.vertices.end = end
+                },
+                _ => batches.push(Batch { pipeline, texture, layer, vertices: start..end, instances: 0..1 })
+            }
+
+        }
+
+        Self::write_growing(&self.device, &self.queue, &mut self.vertex_buffer, bytemuck::cast_slice(vertices.as_slice()));
+        Self::write_growing(&self.device, &self.queue, &mut self.instance_buffer, bytemuck::cast_slice(instances.as_slice()));
+
+        batches
+    }
+
+    // replaces `buffer` with a large enough one first if `contents` do not fit
+    fn write_growing(device: &wgpu::Device, queue: &wgpu::Queue, buffer: &mut wgpu::Buffer, contents: &[u8]) {
+
+        let required = contents.len() as wgpu::BufferAddress;
+
+        if required > buffer.size() {
+            *buffer = Self::create_vertex_buffer(device, required.next_power_of_two());
+        }
+
+        if required > 0 {
+            queue.write_buffer(buffer, 0, contents);
+        }
+    }
+
+    fn create_globals(device: &wgpu::Device, pipelines: &PipelineCache) -> Globals {
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Globals Buffer"),
+            size: std::mem::size_of::<([[f32; 4]; 4], [[f32; 4]; MAX_PARAMETERS])>() as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Globals Bind Group"),
+            layout: pipelines.globals_layout(),
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: buffer.as_entire_binding()
+            }]
+        });
+
+        Globals { buffer, bind_group }
+    }
+
+    fn create_sampler(device: &wgpu::Device) -> wgpu::Sampler {
+        device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        })
+    }
+
+    fn create_texture(&self, image: &Image) -> Texture {
+
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1
+        };
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Entity Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: TEXTURE_FORMAT,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[]
+        });
+        self.write_texture(&texture, image);
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Bind Group"),
+            layout: self.pipelines.texture_layout(),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view)
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler)
+                }
+            ]
+        });
+
+        Texture { texture, bind_group }
+    }
+
+    fn write_texture(&self, texture: &wgpu::Texture, image: &Image) {
+        self.queue.write_texture(
+            texture.as_image_copy(),
+            image.pixels(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: std::num::NonZeroU32::new(image.width() * 4),
+                rows_per_image: std::num::NonZeroU32::new(image.height())
+            },
+            texture.size()
+        );
+    }
+
+    fn create_vertex_buffer(device: &wgpu::Device, size: wgpu::BufferAddress) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shared Vertex Buffer"),
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false
+        })
+    }
+
+    fn draw(&mut self, view: &wgpu::TextureView, frame: &Frame) {
+
+        let batches = self.upload(frame);
+        let [r, g, b, a] = frame.background.clear_color().as_rgba8().map(|channel| channel as f64 / 255.0);
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Render Encoder")
+        });
+
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color { r, g, b, a }),
+                        store: true
+                    }
+                })],
+                depth_stencil_attachment: None
+            });
+
+            render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            // only read by instanced pipelines
+            render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+            let mut bound_pipeline = None;
+            let mut bound_layer = None;
+            let mut bound_texture = None;
+            for batch in batches {
+                if bound_pipeline != Some(batch.pipeline) {
+                    render_pass.set_pipeline(self.pipelines.get(&batch.pipeline));
+                    bound_pipeline = Some(batch.pipeline);
+                    // pipelines without a texture layout may have unbound it
+                    bound_texture = None;
+                }
+                if bound_layer != Some(batch.layer) {
+                    render_pass.set_bind_group(0, &self.globals[batch.layer].bind_group, &[]);
+                    bound_layer = Some(batch.layer);
+                }
+                if let Some(texture) = batch.texture.filter(|&texture| bound_texture != Some(texture)) {
+                    render_pass.set_bind_group(1, &self.textures[texture.0].bind_group, &[]);
+                    bound_texture = Some(texture);
+                }
+                render_pass.draw(batch.vertices, batch.instances);
+            }
+
+        }
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+    }
+
+}
+
+impl Renderer for WgpuRenderer {
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.config.width, self.config.height)
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.config.width = width;
+        self.config.height = height;
+        if let Some(surface) = &self.surface {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    fn register_shader(&mut self, label: &str, source: &str) -> ShaderId {
+        self.pipelines.register_shader(&self.device, label, source)
+    }
+
+    fn register_texture(&mut self, image: &Image) -> TextureId {
+        let texture = self.create_texture(image);
+        self.textures.push(texture);
+        TextureId(self.textures.len() - 1)
+    }
+
+    fn update_texture(&mut self, texture: TextureId, image: &Image) {
+        let current = &self.textures[texture.0].texture;
+        if current.width() == image.width() && current.height() == image.height() {
+            self.write_texture(current, image);
+        } else {
+            self.textures[texture.0] = self.create_texture(image);
+        }
+    }
+
+    fn render(&mut self, frame: &Frame) -> Result<(), RenderError> {
+
+        let output = match &self.surface {
+            Some(surface) => surface.get_current_texture()?,
+            // headless => nothing to present to, see `capture`
+            None => return Ok(())
+        };
+
+        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.draw(&view, frame);
+
+        output.present();
+
+        Ok(())
+    }
+
+    fn capture(&mut self, frame: &Frame) -> Image {
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Offscreen Texture"),
+            size: wgpu::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: self.config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[]
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        self.draw(&view, frame);
+
+        // rows of a texture-to-buffer copy must be padded to a fixed alignment
+        let unpadded_row = self.config.width * 4;
+        let alignment = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let padded_row = unpadded_row.div_ceil(alignment) * alignment;
+
+        let readback = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_row * self.config.height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder")
+        });
+
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &readback,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: std::num::NonZeroU32::new(padded_row),
+                    rows_per_image: None
+                }
+            },
+            texture.size()
+        );
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let slice = readback.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| sender.send(result).unwrap());
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver.recv().unwrap().expect("could not read back offscreen texture");
+
+        let mut pixels = Vec::with_capacity((unpadded_row * self.config.height) as usize);
+        for row in slice.get_mapped_range().chunks(padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row as usize]);
+        }
+        readback.unmap();
+
+        // window surfaces commonly prefer BGRA, which has to be swizzled back into RGBA
+        if matches!(self.config.format, wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb) {
+            for pixel in pixels.chunks_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+
+        Image::from_rgba(self.config.width, self.config.height, pixels)
+    }
+
+}
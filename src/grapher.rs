@@ -0,0 +1,327 @@
+use crate::camera::Camera;
+use crate::colors::Color;
+use crate::entities::{Entity, EntityBuilder, Vertex};
+use crate::expression::{EvaluationError, Expression};
+use crate::stroke::{LineJoin, Stroke};
+
+// world units (logical pixels at the default zoom) per graph unit
+const DEFAULT_SCALE: f64 = 50.0;
+// line widths in logical pixels, kept constant as the camera zooms
+const CURVE_WIDTH: f32 = 2.0;
+const AXIS_WIDTH: f32 = 1.0;
+const DERIVATIVE_DASH: [f32; 2] = [6.0, 4.0];
+
+// samples start this many pixels apart before refinement
+const INITIAL_SPACING: f64 = 8.0;
+// halving an interval more often than this gives up on it
+const MAX_DEPTH: u32 = 12;
+// how far, in pixels, the curve may stray from a straight segment
+const TOLERANCE: f64 = 0.25;
+// a step this many pixels tall across a fully refined interval is a discontinuity
+const JUMP: f64 = 2.0;
+
+type Point = [f64; 2];
+
+struct Graph {
+    function: Box<dyn Fn(f64) -> f64>,
+    color: Color,
+    dashed: bool
+}
+
+// Plots functions of x over whatever part of the plane the camera shows. The
+// curves are resampled whenever the view changes, so zooming in reveals detail
+// instead of magnifying straight segments.
+pub struct Grapher {
+    graphs: Vec<Graph>,
+    scale: f64,
+    axis_color: Color,
+    // the camera and canvas size the last entities were plotted for
+    plotted: Option<(Camera, [f32; 2])>
+}
+
+impl Grapher {
+
+    pub fn new() -> Grapher {
+        Self {
+            graphs: vec![],
+            scale: DEFAULT_SCALE,
+            axis_color: Color::new(110, 110, 110),
+            plotted: None
+        }
+    }
+
+    pub fn plot<F: Fn(f64) -> f64 + 'static>(&mut self, function: F, color: Color) {
+        self.graphs.push(Graph { function: Box::new(function), color, dashed: false });
+        self.plotted = None;
+    }
+
+    // the exact derivative of `expression`, dashed to set it apart from the
+    // functions themselves
+    pub fn plot_derivative(&mut self, expression: &Expression, variable: &str, color: Color) -> Result<(), EvaluationError> {
+        let derivative = expression.derivative(variable).real_function(variable)?;
+        self.graphs.push(Graph { function: Box::new(derivative), color, dashed: true });
+        self.plotted = None;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) {
+        self.graphs.clear();
+        self.plotted = None;
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+        self.plotted = None;
+    }
+
+    pub fn set_axis_color(&mut self, color: Color) {
+        self.axis_color = color;
+        self.plotted = None;
+    }
+
+    pub fn graph_to_world(&self, point: Point) -> [f32; 2] {
+        [(point[0] * self.scale) as f32, (point[1] * self.scale) as f32]
+    }
+
+    pub fn world_to_graph(&self, point: [f32; 2]) -> Point {
+        [point[0] as f64 / self.scale, point[1] as f64 / self.scale]
+    }
+
+    // new entities when the view has changed since the last call, None otherwise
+    pub fn replot(&mut self, camera: &Camera, canvas_size: [f32; 2]) -> Option<Vec<Entity>> {
+        if self.plotted == Some((*camera, canvas_size)) {
+            return None;
+        }
+        self.plotted = Some((*camera, canvas_size));
+        Some(self.entities(camera, canvas_size))
+    }
+
+    // the axes followed by every curve, in the order they were plotted
+    pub fn entities(&self, camera: &Camera, canvas_size: [f32; 2]) -> Vec<Entity> {
+
+        let (min, max) = camera.visible_region(canvas_size);
+        let (min, max) = (self.world_to_graph(min), self.world_to_graph(max));
+        // graph units covered by one logical pixel on screen
+        let pixel = 1.0 / (self.scale * camera.zoom() as f64);
+
+        let mut entities = vec![];
+
+        let axis = Stroke::new(AXIS_WIDTH);
+        entities.extend(self.line(&[[min[0], 0.0], [max[0], 0.0]], self.axis_color, &axis, camera.zoom()));
+        entities.extend(self.line(&[[0.0, min[1]], [0.0, max[1]]], self.axis_color, &axis, camera.zoom()));
+
+        // let curves run a screen beyond the edges so their ends stay hidden
+        let margin = max[1] - min[1];
+        let curve = Stroke::new(CURVE_WIDTH).join(LineJoin::Round);
+        let dashed = curve.clone().dash(DERIVATIVE_DASH.to_vec(), 0.0);
+        for graph in &self.graphs {
+            let stroke = if graph.dashed { &dashed } else { &curve };
+            let runs = sample(&graph.function, min[0], max[0], pixel);
+            for run in clip(&runs, min[1] - margin, max[1] + margin) {
+                entities.extend(self.line(&run, graph.color, stroke, camera.zoom()));
+            }
+        }
+
+        entities
+    }
+
+    fn line(&self, points: &[Point], color: Color, stroke: &Stroke, zoom: f32) -> Option<Entity> {
+        let vertices = points.iter()
+                             .map(|&point| self.graph_to_world(point))
+                             .map(|[x, y]| Vertex::new(x, y, 0.0, color))
+                             .collect();
+        // runs that collapse to a single point have nothing to draw
+        EntityBuilder::from_stroke(vertices, stroke, zoom).ok().map(|builder| builder.build())
+    }
+
+}
+
+impl Default for Grapher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Samples `function` between `x_min` and `x_max`, subdividing wherever a straight
+// segment would stray from the curve by more than TOLERANCE pixels. The curve is
+// cut into separate runs wherever it is undefined or jumps.
+pub fn sample(function: &dyn Fn(f64) -> f64, x_min: f64, x_max: f64, pixel: f64) -> Vec<Vec<Point>> {
+
+    let mut sampler = Sampler { function, pixel, runs: vec![], current: vec![] };
+    let intervals = ((x_max - x_min) / (pixel * INITIAL_SPACING)).ceil().max(1.0) as usize;
+
+    let mut previous = sampler.evaluate(x_min);
+    if previous[1].is_finite() {
+        sampler.current.push(previous);
+    }
+    for i in 1..=intervals {
+        let next = sampler.evaluate(x_min + (x_max - x_min) * i as f64 / intervals as f64);
+        sampler.refine(previous, next, 0);
+        previous = next;
+    }
+
+    sampler.end_run();
+    sampler.runs
+}
+
+struct Sampler<'a> {
+    function: &'a dyn Fn(f64) -> f64,
+    pixel: f64,
+    runs: Vec<Vec<Point>>,
+    current: Vec<Point>
+}
+
+impl<'a> Sampler<'a> {
+
+    fn evaluate(&self, x: f64) -> Point {
+        [x, (self.function)(x)]
+    }
+
+    fn end_run(&mut self) {
+        let run = std::mem::take(&mut self.current);
+        if run.len() >= 2 {
+            self.runs.push(run);
+        }
+    }
+
+    // extends the current run from `a`, already sampled, up to `b`
+    fn refine(&mut self, a: Point, b: Point, depth: u32) {
+
+        let middle = self.evaluate((a[0] + b[0]) / 2.0);
+        let defined = a[1].is_finite() && b[1].is_finite() && middle[1].is_finite();
+
+        if defined {
+            let deviation = (middle[1] - (a[1] + b[1]) / 2.0).abs() / self.pixel;
+            if deviation > TOLERANCE && depth < MAX_DEPTH {
+                self.refine(a, middle, depth + 1);
+                self.refine(middle, b, depth + 1);
+                return;
+            }
+            if deviation > TOLERANCE && (b[1] - a[1]).abs() / self.pixel > JUMP {
+                self.end_run();
+            }
+            self.current.push(b);
+            return;
+        }
+
+        // assume nothing is hiding between three undefined samples
+        if [a, middle, b].iter().all(|point| !point[1].is_finite()) {
+            return;
+        }
+
+        if depth < MAX_DEPTH {
+            self.refine(a, middle, depth + 1);
+            self.refine(middle, b, depth + 1);
+            return;
+        }
+
+        // the edge of the domain is somewhere inside this sliver
+        self.end_run();
+        if b[1].is_finite() {
+            self.current.push(b);
+        }
+    }
+
+}
+
+// trims runs to the band between `y_min` and `y_max`, splitting them where they leave it
+pub fn clip(runs: &[Vec<Point>], y_min: f64, y_max: f64) -> Vec<Vec<Point>> {
+
+    let mut clipped = vec![];
+
+    for run in runs {
+        let mut current: Vec<Point> = vec![];
+        for pair in run.windows(2) {
+            let (p, q) = (pair[0], pair[1]);
+            let (mut enter, mut exit) = (0.0, 1.0);
+            let dy = q[1] - p[1];
+            if dy == 0.0 {
+                if p[1] < y_min || p[1] > y_max {
+                    exit = -1.0;
+                }
+            } else {
+                let (t_min, t_max) = ((y_min - p[1]) / dy, (y_max - p[1]) / dy);
+                enter = f64::max(enter, t_min.min(t_max));
+                exit = f64::min(exit, t_min.max(t_max));
+            }
+
+            if enter > exit {
+                if current.len() >= 2 {
+                    clipped.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                continue;
+            }
+
+            let at = |t: f64| [p[0] + (q[0] - p[0]) * t, p[1] + dy * t];
+            if current.is_empty() {
+                current.push(at(enter));
+            }
+            current.push(at(exit));
+            if exit < 1.0 {
+                clipped.push(std::mem::take(&mut current));
+            }
+        }
+        if current.len() >= 2 {
+            clipped.push(current);
+        }
+    }
+
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn refined_curve_stays_within_tolerance() {
+        let pixel = 0.01;
+        let runs = sample(&|x: f64| (3.0 * x).sin(), -3.0, 3.0, pixel);
+        assert_eq!(runs.len(), 1);
+        for pair in runs[0].windows(2) {
+            let x = (pair[0][0] + pair[1][0]) / 2.0;
+            let chord = (pair[0][1] + pair[1][1]) / 2.0;
+            assert!(((3.0 * x).sin() - chord).abs() / pixel <= TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn undefined_regions_split_the_curve() {
+        assert!(sample(&|_| f64::NAN, -3.0, 3.0, 0.01).is_empty());
+
+        let runs = sample(&|x: f64| (x * x - 1.0).sqrt(), -3.0, 3.0, 0.01);
+        assert_eq!(runs.len(), 2);
+        assert!(runs[0].last().unwrap()[0] < -0.999);
+        assert!(runs[1][0][0] > 0.999);
+    }
+
+    #[test]
+    fn jumps_split_the_curve() {
+        let runs = sample(&|x: f64| x.floor(), -0.5, 2.5, 0.01);
+        assert_eq!(runs.len(), 4);
+        assert!(runs.iter().all(|run| run.iter().all(|p| p[1] == run[0][1])));
+    }
+
+    #[test]
+    fn clipping_cuts_runs_at_the_band() {
+        let runs = vec![vec![[0.0, 0.0], [1.0, 10.0], [2.0, 0.0]]];
+        let clipped = clip(&runs, -1.0, 5.0);
+        assert_eq!(clipped, vec![vec![[0.0, 0.0], [0.5, 5.0]], vec![[1.5, 5.0], [2.0, 0.0]]]);
+    }
+
+    #[test]
+    fn derivatives_are_plotted_exactly() {
+        let mut grapher = Grapher::new();
+        grapher.plot_derivative(&Expression::parse("x^3 - 2*x").unwrap(), "x", Color::new(0, 0, 255)).unwrap();
+        assert!(grapher.graphs[0].dashed);
+        assert_eq!((grapher.graphs[0].function)(2.0), 10.0);
+        assert!(grapher.plot_derivative(&Expression::parse("x*y").unwrap(), "x", Color::new(0, 0, 255)).is_err());
+    }
+
+}
@@ -0,0 +1,127 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+#[derive(Clone)]
+pub struct Image {
+    width: u32,
+    height: u32,
+    // tightly packed RGBA8, rows ordered top to bottom
+    pixels: Vec<u8>
+}
+
+impl Image {
+
+    pub fn new(width: u32, height: u32) -> Image {
+        Self {
+            width,
+            height,
+            pixels: vec![0; (width * height * 4) as usize]
+        }
+    }
+
+    pub fn from_rgba(width: u32, height: u32, pixels: Vec<u8>) -> Image {
+        if pixels.len() != (width * height * 4) as usize {
+            panic!("{} bytes cannot hold a {}x{} RGBA image", pixels.len(), width, height)
+        }
+        Self { width, height, pixels }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    fn offset(&self, x: u32, y: u32) -> usize {
+        if x >= self.width || y >= self.height {
+            panic!("pixel ({}, {}) out of bounds for a {}x{} image", x, y, self.width, self.height)
+        }
+        ((y * self.width + x) * 4) as usize
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+        let offset = self.offset(x, y);
+        let mut rgba = [0; 4];
+        rgba.copy_from_slice(&self.pixels[offset..offset + 4]);
+        rgba
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, rgba: [u8; 4]) {
+        let offset = self.offset(x, y);
+        self.pixels[offset..offset + 4].copy_from_slice(&rgba);
+    }
+
+    // bilinear filtering with edges clamped, as a linear wgpu sampler would;
+    // texel coordinates put pixel (x, y) between x..x + 1 and y..y + 1
+    pub fn sample(&self, texel: [f32; 2]) -> [f32; 4] {
+        let (x, y) = (texel[0] - 0.5, texel[1] - 0.5);
+        let (left, top) = (x.floor(), y.floor());
+        let (fx, fy) = (x - left, y - top);
+        let clamped = |value: f32, size: u32| value.clamp(0.0, (size - 1) as f32) as u32;
+        let (x0, x1) = (clamped(left, self.width), clamped(left + 1.0, self.width));
+        let (y0, y1) = (clamped(top, self.height), clamped(top + 1.0, self.height));
+        let [a, b, c, d] = [(x0, y0), (x1, y0), (x0, y1), (x1, y1)].map(|(x, y)| self.pixel(x, y));
+        [0, 1, 2, 3].map(|i| {
+            let upper = a[i] as f32 * (1.0 - fx) + b[i] as f32 * fx;
+            let lower = c[i] as f32 * (1.0 - fx) + d[i] as f32 * fx;
+            (upper * (1.0 - fy) + lower * fy) / 255.0
+        })
+    }
+
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        let file = File::create(path).map_err(ImageError::Io)?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(ImageError::Encoding)?;
+        writer.write_image_data(&self.pixels).map_err(ImageError::Encoding)
+    }
+
+    pub fn load_png<P: AsRef<Path>>(path: P) -> Result<Image, ImageError> {
+        let file = File::open(path).map_err(ImageError::Io)?;
+        let mut decoder = png::Decoder::new(file);
+        // expands palettes and low bit depths so every format lands on 8-bit channels
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info().map_err(ImageError::Decoding)?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).map_err(ImageError::Decoding)?;
+        buffer.truncate(info.buffer_size());
+
+        let pixels = match info.color_type {
+            png::ColorType::Rgba => buffer,
+            png::ColorType::Rgb => buffer.chunks(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect(),
+            png::ColorType::GrayscaleAlpha => buffer.chunks(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+            png::ColorType::Grayscale => buffer.iter().flat_map(|&p| [p, p, p, 255]).collect(),
+            png::ColorType::Indexed => return Err(ImageError::UnsupportedFormat)
+        };
+
+        Ok(Self::from_rgba(info.width, info.height, pixels))
+    }
+
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Encoding(png::EncodingError),
+    Decoding(png::DecodingError),
+    UnsupportedFormat
+}
+
+impl std::fmt::Display for ImageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Could not access image file: {}", err),
+            Self::Encoding(err) => write!(f, "Could not encode PNG: {}", err),
+            Self::Decoding(err) => write!(f, "Could not decode PNG: {}", err),
+            Self::UnsupportedFormat => write!(f, "PNG color type cannot be converted to RGBA")
+        }
+    }
+}
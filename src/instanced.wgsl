@@ -0,0 +1,39 @@
+// Vertex shader
+struct Globals {
+	view_projection: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+struct VertexIn {
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec3<f32>
+}
+
+// one copy of the vertices, see `Instance`
+struct InstanceIn {
+	@location(2) offset: vec2<f32>,
+	@location(3) scale: f32,
+	@location(4) color: vec3<f32>
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec3<f32>
+}
+
+@vertex
+fn vertex_shader_main(model: VertexIn, instance: InstanceIn) -> VertexOut {
+	var out: VertexOut;
+	let placed = vec3<f32>(model.position.xy * instance.scale + instance.offset, model.position.z);
+	out.position = globals.view_projection * vec4<f32>(placed, 1.0);
+	out.color = model.color * instance.color;
+	return out;
+}
+
+// Fragment shader
+@fragment
+fn fragment_shader_main(in: VertexOut) -> @location(0) vec4<f32> {
+	return vec4<f32>(in.color, 1.0);
+}
@@ -1,42 +1,255 @@
-
 #![allow(dead_code)]
 
+mod button;
+mod camera;
 mod colors;
+mod complex;
 mod entities;
+mod expression;
+mod gpu;
+mod grapher;
+mod image;
 mod marlin;
+mod newton;
+mod particles;
+mod phase_portrait;
+mod physics;
+mod pipeline;
+mod polygon;
+mod renderer;
+mod root_picker;
+mod roots;
+mod simulation;
+mod slider;
+mod software;
+mod stroke;
+mod symbolic;
+mod text;
+mod transform;
+#[cfg(test)]
+mod snapshot;
 
 use winit::window::{WindowBuilder};
 use winit::event_loop::EventLoop;
 
-use colors::{BLUE, RED, WHITE};
-use entities::{ShapeKind, Vertex};
+use hebrides::linal::Vector;
+
+use button::ButtonAction;
+use colors::{Color, BLACK, BLUE, RED, WHITE};
+use entities::{EntityBuilder, ShapeKind, Vertex};
+use expression::Expression;
 use marlin::{MasterWindowState, SceneName};
+use particles::{Emitter, ParticleSystem};
+use physics::{Body, World};
+use roots::Polynomial;
+use simulation::{Integrator, OdeSystem, State};
+use slider::Slider;
+use text::{Align, Text};
 
-#[tokio::main]
-async fn main() {
+const EXPORT_WIDTH: u32 = 800;
+const EXPORT_HEIGHT: u32 = 600;
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
-    let mut state = MasterWindowState::new(window).await;
+// gravitational parameter of the Simulation scene's star, in pixels^3 / s^2
+const STAR_GM: f64 = 4.0e6;
+// where the star sits, leaving the right of the scene to the rigid bodies
+const STAR: [f64; 2] = [-200.0, 80.0];
+const GRAVITY: f32 = -600.0;
+
+// corners of an axis-aligned box, in the order ShapeKind::Rectangle expects
+fn corners(min: [f32; 2], max: [f32; 2], color: Color) -> Vec<Vertex> {
+    vec![
+        Vertex::new(min[0], max[1], 0.0, color),
+        Vertex::new(min[0], min[1], 0.0, color),
+        Vertex::new(max[0], min[1], 0.0, color),
+        Vertex::new(max[0], max[1], 0.0, color)
+    ]
+}
 
-    state.add_shape(&SceneName::Home, &ShapeKind::Circle(500.0), vec![Vertex::new(0.0, 0.0, 0.0, BLUE)]);
+// the Grapher's functions, the sine wave scaled by `amplitude`
+fn plot_graphs(state: &mut MasterWindowState, amplitude: f64) {
+    let wave = format!("({})*sin(x)", amplitude);
+    state.grapher_mut().clear();
+    for (formula, color) in [(wave.as_str(), BLUE), ("tan(x)", RED), ("sqrt(x)", WHITE)] {
+        let function = Expression::parse(formula).unwrap().real_function("x").unwrap();
+        state.grapher_mut().plot(function, color);
+    }
+    state.grapher_mut().plot_derivative(&Expression::parse(&wave).unwrap(), "x", BLUE).unwrap();
+}
 
-    state.add_button(&SceneName::Home, &ShapeKind::Rectangle, vec![
-        Vertex::new(-200.0, 50.0, 0.0, WHITE),
-        Vertex::new(-200.0, -50.0, 0.0, WHITE),
-        Vertex::new(200.0, -50.0, 0.0, WHITE),
-        Vertex::new(200.0, 50.0, 0.0, WHITE)
+fn populate(state: &mut MasterWindowState) {
+
+    state.add_shape(&SceneName::Home, &ShapeKind::Circle(250.0), vec![Vertex::new(0.0, 0.0, 0.0, BLUE)]);
+    state.add_text(&SceneName::Home, &Text::new("Marlin", 64.0).align(Align::Center), [0.0, 150.0]);
+
+    let start = state.add_button(&SceneName::Home, &ShapeKind::Rectangle, vec![
+        Vertex::new(-100.0, 25.0, 0.0, WHITE),
+        Vertex::new(-100.0, -25.0, 0.0, WHITE),
+        Vertex::new(100.0, -25.0, 0.0, WHITE),
+        Vertex::new(100.0, 25.0, 0.0, WHITE)
     ], state.next_scene());
+    state.button_mut(start).set_label(Some(Text::new("Start", 24.0).color(BLACK)));
+    state.button_mut(start).set_tooltip(Some("Drag the roots of a polynomial around the complex plane"));
+
+    // z^5 + z^2 - z - 1/2
+    let report = state.root_picker_mut().set_polynomial(Polynomial::from_real(&[-0.5, -1.0, 1.0, 0.0, 0.0, 1.0])).unwrap();
+    if !report.converged {
+        eprintln!("Root finding stopped after {} iterations with residual {}", report.iterations, report.residual);
+    }
+
+    // every scene past Home links to its neighbours from the top corners
+    for scene in [SceneName::RootPicker, SceneName::Grapher, SceneName::PhasePortrait, SceneName::Simulation] {
+        for (label, min, destination) in [("Back", [-380.0, 240.0], scene.previous()), ("Next", [280.0, 240.0], scene.next())] {
+            let button = state.add_button(&scene, &ShapeKind::Rectangle, corners(min, [min[0] + 100.0, min[1] + 40.0], WHITE), destination);
+            let name: String = destination.into();
+            state.button_mut(button).set_label(Some(Text::new(label, 20.0).color(BLACK)));
+            state.button_mut(button).set_tooltip(Some(&name));
+        }
+    }
+
+    // kept clear of the roots near the origin
+    let fractal = state.add_button(&SceneName::RootPicker, &ShapeKind::Rectangle, corners([140.0, -270.0], [300.0, -220.0], WHITE), ButtonAction::run(|state| {
+        let shown = state.root_picker().fractal().is_some();
+        state.show_newton_fractal(!shown);
+    }));
+    state.button_mut(fractal).set_label(Some(Text::new("Fractal", 24.0).color(BLACK)));
+
+    plot_graphs(state, 1.0);
+    state.add_slider(
+        Slider::new(SceneName::Grapher, [-100.0, -260.0], 200.0, -2.0, 2.0)
+            .step(0.1)
+            .initial(1.0)
+            .label("Amplitude")
+            .on_change(plot_graphs)
+    );
+
+    // a damped pendulum: angle against angular velocity, spiralling into rest
+    let pendulum = |damping: f64| move |x: f64, y: f64| [y, -x.sin() - damping * y];
+    state.phase_portrait_mut().set_field(pendulum(0.25));
+    for start in [[2.0, 0.0], [-3.0, 2.5], [3.0, -2.5]] {
+        state.phase_portrait_mut().add_trajectory(start);
+    }
+    let clear = state.add_button(&SceneName::PhasePortrait, &ShapeKind::Rectangle, corners([-380.0, -280.0], [-260.0, -240.0], WHITE), ButtonAction::run(|state| {
+        state.phase_portrait_mut().clear_trajectories();
+    }));
+    state.button_mut(clear).set_label(Some(Text::new("Clear", 20.0).color(BLACK)));
+    state.add_slider(
+        Slider::new(SceneName::PhasePortrait, [-220.0, -260.0], 200.0, 0.0, 1.0)
+            .step(0.05)
+            .initial(0.25)
+            .label("Damping")
+            .on_change(move |state, damping| state.phase_portrait_mut().set_field(pendulum(damping)))
+    );
 
-    state.add_shape(&SceneName::RootPicker, &ShapeKind::Circle(500.0), vec![Vertex::new(0.0, 0.0, 0.0, RED)]);
+    // one planet per integrator on the same kind of circular orbit; Euler's
+    // slowly spirals outwards while the others stay put
+    let [star_x, star_y] = STAR;
+    state.add_shape(&SceneName::Simulation, &ShapeKind::Circle(16.0), vec![Vertex::new(star_x as f32, star_y as f32, 0.0, Color::new(255, 200, 60))]);
+    let gravity = |_: f64, y: &State| {
+        let cubed = (y[0] * y[0] + y[1] * y[1]).powf(1.5);
+        Vector::new(vec![y[2], y[3], -STAR_GM * y[0] / cubed, -STAR_GM * y[1] / cubed])
+    };
+    let planets = [
+        (Integrator::Euler, 60.0, RED),
+        (Integrator::RungeKutta4, 100.0, WHITE),
+        (Integrator::dormand_prince(1e-9), 140.0, BLUE),
+        (Integrator::Verlet(2), 180.0, Color::new(120, 220, 120))
+    ];
+    for (integrator, radius, color) in planets {
+        let speed = (STAR_GM / radius).sqrt();
+        let draw = move |y: &State| vec![
+            EntityBuilder::from_shape(ShapeKind::Circle(6.0), vec![Vertex::new((star_x + y[0]) as f32, (star_y + y[1]) as f32, 0.0, color)]).unwrap().build()
+        ];
+        state.simulation_mut().add(OdeSystem::new(gravity, Vector::new(vec![radius, 0.0, 0.0, speed]), integrator, draw));
+    }
 
-    state.add_button(&SceneName::RootPicker, &ShapeKind::Rectangle, vec![
-        Vertex::new(-200.0, 50.0, 0.0, WHITE),
-        Vertex::new(-200.0, -50.0, 0.0, WHITE),
-        Vertex::new(200.0, -50.0, 0.0, WHITE),
-        Vertex::new(200.0, 50.0, 0.0, WHITE)
-    ], state.previous_scene());
+    let mut fountain = ParticleSystem::new(3.0);
+    fountain.set_acceleration([0.0, -300.0]);
+    fountain.set_emitter(Some(
+        Emitter::new([-200.0, -300.0], 600.0)
+            .direction(std::f32::consts::FRAC_PI_2, 0.2)
+            .speed(250.0, 330.0)
+            .lifetime(2.0, 3.0)
+            .colors(vec![BLUE, WHITE, Color::new(120, 200, 255)])
+    ));
+    state.simulation_mut().add(fountain);
+
+    // a bin with a ramp, and a mix of boxes, a wedge and balls dropped into it
+    let gray = Color::new(150, 150, 150);
+    let mut world = World::new([0.0, GRAVITY]);
+    for (min, max) in [([20.0, -290.0], [390.0, -260.0]), ([20.0, -260.0], [35.0, -120.0]), ([375.0, -260.0], [390.0, -120.0])] {
+        world.add(Body::from_shape(ShapeKind::Rectangle, corners(min, max, gray), 0.0).unwrap());
+    }
+    world.add(Body::from_shape(ShapeKind::Polygon, vec![
+        Vertex::new(60.0, -20.0, 0.0, gray),
+        Vertex::new(60.0, -35.0, 0.0, gray),
+        Vertex::new(230.0, -110.0, 0.0, gray),
+        Vertex::new(230.0, -95.0, 0.0, gray)
+    ], 0.0).unwrap());
+    for (i, x) in [260.0, 290.0, 320.0].into_iter().enumerate() {
+        let y = -230.0 + 45.0 * i as f32;
+        world.add(Body::from_shape(ShapeKind::Rectangle, corners([x, y], [x + 40.0, y + 30.0], RED), 1.0).unwrap());
+    }
+    world.add(Body::from_shape(ShapeKind::Triangle, vec![
+        Vertex::new(90.0, 60.0, 0.0, WHITE),
+        Vertex::new(130.0, 60.0, 0.0, WHITE),
+        Vertex::new(110.0, 95.0, 0.0, WHITE)
+    ], 1.0).unwrap().moving([0.0, 0.0], 2.0));
+    for (x, y, radius) in [(80.0, 20.0, 12.0), (120.0, 140.0, 16.0), (180.0, 200.0, 10.0), (330.0, 120.0, 20.0)] {
+        world.add(Body::from_shape(ShapeKind::Circle(radius), vec![Vertex::new(x, y, 0.0, BLUE)], 1.0).unwrap().restitution(0.6));
+    }
+    state.simulation_mut().add(world);
+    state.add_slider(
+        Slider::new(SceneName::Simulation, [-100.0, 250.0], 200.0, 30.0, 240.0)
+            .step(10.0)
+            .initial(1.0 / state.simulation().timestep())
+            .label("Steps per second")
+            .on_change(|state, rate| state.simulation_mut().set_timestep(1.0 / rate))
+    );
+
+}
+
+// marlin --export <scene> <file.png> [--software] renders a single scene without
+// opening a window, optionally skipping the GPU entirely
+async fn export(scene: &str, path: &str, software: bool) {
+
+    let scene: SceneName = match scene.parse() {
+        Ok(scene) => scene,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut state = if software {
+        MasterWindowState::software(EXPORT_WIDTH, EXPORT_HEIGHT)
+    } else {
+        MasterWindowState::headless(EXPORT_WIDTH, EXPORT_HEIGHT).await
+    };
+    populate(&mut state);
+
+    if let Err(err) = state.export_scene(scene, path) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+
+}
+
+#[tokio::main]
+async fn main() {
+
+    let args: Vec<String> = std::env::args().collect();
+    if let [_, flag, scene, path, rest @ ..] = args.as_slice() {
+        if flag == "--export" {
+            export(scene, path, rest.iter().any(|arg| arg == "--software")).await;
+            return;
+        }
+    }
+
+    let event_loop = EventLoop::new();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut state = MasterWindowState::new(window).await;
 
+    populate(&mut state);
 
     state.run(event_loop).await;
-}
\ No newline at end of file
+}
@@ -6,18 +6,61 @@ use winit::{
 };
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
 
+use crate::button::{Button, ButtonAction, ButtonId};
+use crate::camera::Camera;
+use crate::colors::Background;
 use crate::entities::{Entity, Vertex, EntityBuilder, ShapeKind};
-// use crate::colors::{RED, BLUE};
+use crate::gpu::WgpuRenderer;
+use crate::grapher::Grapher;
+use crate::image::{Image, ImageError};
+use crate::newton;
+use crate::phase_portrait::PhasePortrait;
+use crate::pipeline::{ShaderId, TextureId};
+use crate::renderer::{Frame, RenderError, Renderer};
+use crate::root_picker::RootPicker;
+use crate::simulation::Simulation;
+use crate::stroke::Stroke;
+use crate::slider::{Slider, SliderId};
+use crate::software::SoftwareRenderer;
+use crate::text::{Align, Text, Typesetter};
+use crate::transform::Transform;
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 pub enum SceneName {
     Home,
     RootPicker,
     Grapher,
+    PhasePortrait,
     Simulation
 }
 
+impl SceneName {
+
+    pub fn next(&self) -> SceneName {
+        match self {
+            Self::Home => Self::RootPicker,
+            Self::RootPicker => Self::Grapher,
+            Self::Grapher => Self::PhasePortrait,
+            Self::PhasePortrait => Self::Simulation,
+            Self::Simulation => Self::Home
+        }
+    }
+
+    pub fn previous(&self) -> SceneName {
+        match self {
+            Self::Home => Self::Home,
+            Self::RootPicker => Self::Home,
+            Self::Grapher => Self::RootPicker,
+            Self::PhasePortrait => Self::Grapher,
+            Self::Simulation => Self::PhasePortrait
+        }
+    }
+
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<String> for SceneName {
     fn into(self) -> String {
@@ -25,431 +68,759 @@ impl Into<String> for SceneName {
             Self::Home => "Home".to_string(),
             Self::RootPicker => "RootPicker".to_string(),
             Self::Grapher => "Grapher".to_string(),
+            Self::PhasePortrait => "PhasePortrait".to_string(),
             Self::Simulation => "Simulation".to_string()
         }
     }
 }
 
-pub struct ButtonDimensions {
-    horizontal: f32,
-    vertical: f32
+impl std::str::FromStr for SceneName {
+    type Err = UnknownSceneError;
+
+    fn from_str(name: &str) -> Result<SceneName, UnknownSceneError> {
+        match name {
+            "Home" => Ok(Self::Home),
+            "RootPicker" => Ok(Self::RootPicker),
+            "Grapher" => Ok(Self::Grapher),
+            "PhasePortrait" => Ok(Self::PhasePortrait),
+            "Simulation" => Ok(Self::Simulation),
+            _ => Err(UnknownSceneError(name.to_string()))
+        }
+    }
 }
 
-impl ButtonDimensions {
+#[derive(Debug)]
+pub struct UnknownSceneError(String);
 
-    pub fn new(horizontal: f32, vertical: f32) -> ButtonDimensions {
-        Self { horizontal, vertical }
+impl std::fmt::Display for UnknownSceneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "There is no scene named {}", self.0)
     }
-
 }
 
-pub struct Button {
-    inhabiting_scene: SceneName,
-    center: Vertex,
-    scene_request: SceneName,
-    entity: Entity,
-    dimensions: ButtonDimensions
+// window coordinates are physical pixels from the top left corner; canvas
+// coordinates are logical pixels from the middle of the window with y pointing
+// up, matching the world space entities are laid out in
+pub struct MousePosition {
+    x: f64,
+    y: f64,
+    window_dimensions: (f64, f64),
+    scale_factor: f64,
+    // canvas point before the latest movement
+    previous: [f32; 2],
+    // canvas point the left button went down at, None while it is up
+    pressed_at: Option<[f32; 2]>
 }
 
-impl Button {
-
-    pub fn new(inhabiting_scene: SceneName, scene_request: SceneName, entity: Entity) -> Button {
-        
-        let dimensions = ButtonDimensions::new(
-            (Self::leftmost_value(&entity) - Self::rightmost_value(&entity)).abs(),
-            (Self::bottommost_value(&entity) - Self::topmost_value(&entity)).abs()
-        );
-
-        let center = Vertex::average(&entity.vertices);
+impl MousePosition {
 
+    pub fn new(x: f64, y: f64, window_width: f64, window_height: f64) -> MousePosition {
         Self {
-            inhabiting_scene,
-            center,
-            scene_request,
-            entity,
-            dimensions
-        }
-    }
-
-    fn leftmost_value(entity: &Entity) -> f32 {
-        let vertices = &entity.vertices;
-        let mut leftmost = &vertices[0];
-        for vertex in &vertices[1..vertices.len()] {
-            if vertex.position[0] < leftmost.position[0] {
-                leftmost = vertex;
-            }
+            x, y,
+            window_dimensions: (window_width, window_height),
+            scale_factor: 1.0,
+            previous: [0.0, 0.0],
+            pressed_at: None
         }
-        leftmost.position[0] * entity.surface_dimensions.horizontal
     }
 
-    fn rightmost_value(entity: &Entity) -> f32 {
-        let vertices = &entity.vertices;
-        let mut rightmost = &vertices[0];
-        for vertex in &vertices[1..vertices.len()] {
-            if vertex.position[0] > rightmost.position[0] {
-                rightmost = vertex;
-            }
-        }
-        rightmost.position[0] * entity.surface_dimensions.horizontal
+    pub fn update_window_dimensions(&mut self, horizontal: f64, vertical: f64) {
+        self.window_dimensions = (horizontal, vertical);
     }
 
-    fn topmost_value(entity: &Entity) -> f32 {
-        let vertices = &entity.vertices;
-        let mut topmost = &vertices[0];
-        for vertex in &vertices[1..vertices.len()] {
-            if vertex.position[1] > topmost.position[1] {
-                topmost = vertex;
-            }
-        }
-        topmost.position[1] * entity.surface_dimensions.vertical
+    pub fn update_scale_factor(&mut self, scale_factor: f64) {
+        self.scale_factor = scale_factor;
     }
 
-    fn bottommost_value(entity: &Entity) -> f32 {
-        let vertices = &entity.vertices;
-        let mut bottommost = &vertices[0];
-        for vertex in &vertices[1..vertices.len()] {
-            if vertex.position[1] < bottommost.position[1] {
-                bottommost = vertex;
-            }
-        }
-        bottommost.position[1] * entity.surface_dimensions.vertical
+    pub fn update_from_canvas_coords(&mut self, new_x: f64, new_y: f64) {
+        let corrected_x = new_x * self.scale_factor + self.window_dimensions.0 / 2.0;
+        let corrected_y = self.window_dimensions.1 / 2.0 - new_y * self.scale_factor;
+        self.previous = self.canvas();
+        self.x = corrected_x;
+        self.y = corrected_y;
     }
 
-    pub fn left_bound(&self) -> f64 {
-        (self.center.position[0] - self.dimensions.horizontal / 3.5) as f64
+    pub fn update_from_window_coords(&mut self, new_x: f64, new_y: f64) {
+        self.previous = self.canvas();
+        self.x = new_x;
+        self.y = new_y;
     }
 
-    pub fn right_bound(&self) -> f64 {
-        (self.center.position[0] + self.dimensions.horizontal / 3.5) as f64
+    pub fn canvas(&self) -> [f32; 2] {
+        [self.canvas_x() as f32, self.canvas_y() as f32]
     }
 
-    pub fn top_bound(&self) -> f64 {
-        (self.center.position[1] + self.dimensions.vertical / 3.5) as f64
+    // canvas points before and after the latest movement
+    pub fn movement(&self) -> ([f32; 2], [f32; 2]) {
+        (self.previous, self.canvas())
     }
 
-    pub fn bottom_bound(&self) -> f64 {
-        (self.center.position[1] - self.dimensions.vertical / 3.5) as f64
+    pub fn press(&mut self) {
+        self.pressed_at = Some(self.canvas());
     }
 
-}
-
-pub struct MousePosition {
-    x: f64,
-    y: f64,
-    window_dimensions: (f64, f64)
-}
-
-impl MousePosition {
-
-    pub fn new(x: f64, y: f64, window_width: f64, window_height: f64) -> MousePosition {
-        Self {
-            x, y,
-            window_dimensions: (window_width, window_height)
-        }
+    // where the press that just ended began
+    pub fn release(&mut self) -> Option<[f32; 2]> {
+        self.pressed_at.take()
     }
 
-    pub fn update_window_dimensions(&mut self, horizontal: f64, vertical: f64) {
-        self.window_dimensions = (horizontal, vertical);
+    pub fn pressed_at(&self) -> Option<[f32; 2]> {
+        self.pressed_at
     }
 
-    pub fn update_from_canvas_coords(&mut self, new_x: f64, new_y: f64) {
-        let corrected_x = new_x + self.window_dimensions.0 / 2.0;
-        let corrected_y = new_y + self.window_dimensions.1 / 2.0;
-        self.x = corrected_x;
-        self.y = corrected_y;
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
     }
 
-    pub fn update_from_window_coords(&mut self, new_x: f64, new_y: f64) {
-        self.x = new_x;
-        self.y = new_y;
+    pub fn world_coords(&self, camera: &Camera) -> (f64, f64) {
+        let [x, y] = camera.canvas_to_world(self.canvas());
+        (x as f64, y as f64)
     }
 
     pub fn canvas_x(&self) -> f64 {
-        self.x - self.window_dimensions.0 / 2.0
+        (self.x - self.window_dimensions.0 / 2.0) / self.scale_factor
     }
 
     pub fn canvas_y(&self) -> f64 {
-        -(self.y - self.window_dimensions.1 / 2.0)
+        -(self.y - self.window_dimensions.1 / 2.0) / self.scale_factor
     }
 
-    fn within_horizontal_bounds(&self, x_left: f64, x_right: f64) -> bool {
-        self.canvas_x() >= x_left && self.canvas_x() <= x_right
-    }
+}
 
-    fn within_vertical_bounds(&self, y_bottom: f64, y_top: f64) -> bool {
-        self.canvas_y() >= y_bottom && self.canvas_y() <= y_top
-    }
 
-    pub fn between(&self, x_left: f64, x_right: f64, y_bottom: f64, y_top: f64) -> bool {
-        self.within_horizontal_bounds(x_left, x_right) && self.within_vertical_bounds(y_bottom, y_top)
+pub struct Scene {
+    entities: Vec<Entity>,
+    background: Background,
+    camera: Camera,
+    // whether dragging and scrolling move the camera
+    navigable: bool,
+    // which of `entities` are lines, with what they were tessellated from, so
+    // they can be tessellated again as the camera zooms
+    lines: Vec<(usize, Vec<Vertex>, Stroke)>,
+    lines_zoom: f32,
+    // rebuilt from the view, e.g. plotted curves, and drawn over `entities`
+    generated: Vec<Entity>,
+    // read by custom shaders, kept up to date along with `generated`
+    parameters: Vec<[f32; 4]>,
+    // text over the scene that reads out its state, kept up to date along with
+    // `generated` but drawn with the buttons
+    readout: Option<Entity>
+}
+
+impl Scene {
+
+    pub fn new(navigable: bool) -> Scene {
+        Self {
+            entities: vec![],
+            background: Background::default(),
+            camera: Camera::new(),
+            navigable,
+            lines: vec![],
+            lines_zoom: 1.0,
+            generated: vec![],
+            parameters: vec![],
+            readout: None
+        }
     }
 
 }
 
+// what the left mouse button is holding on to
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Drag {
+    Camera,
+    // index into the RootPicker's roots
+    Root(usize),
+    // index into the window state's sliders
+    Slider(usize)
+}
+
+// a press released within this many logical pixels of where it began is a
+// click rather than a drag
+const CLICK_DISTANCE: f32 = 3.0;
+// each line of scrolling zooms by this factor
+const ZOOM_PER_SCROLL_LINE: f32 = 1.1;
+// how many pixels of touchpad scrolling count as one line
+const PIXELS_PER_SCROLL_LINE: f64 = 20.0;
 
 pub struct MasterWindowState {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    config: wgpu::SurfaceConfiguration,
+    renderer: Box<dyn Renderer>,
     size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
+    // physical pixels per logical pixel, 1.0 when headless
+    scale_factor: f64,
+    // None => headless, the state can be rendered offscreen but never run
+    window: Option<Window>,
     cur_scene: SceneName,
     buttons: Vec<Button>,
-    scenes: HashMap<SceneName, Vec<Entity>>,
-    mouse_position: MousePosition
+    sliders: Vec<Slider>,
+    scenes: HashMap<SceneName, Scene>,
+    mouse_position: MousePosition,
+    dragging: Option<Drag>,
+    grapher: Grapher,
+    phase_portrait: PhasePortrait,
+    root_picker: RootPicker,
+    newton_shader: ShaderId,
+    simulation: Simulation,
+    typesetter: Typesetter,
+    // the typesetter's glyph atlas as the renderer knows it
+    glyphs: TextureId,
+    // None until the first update
+    last_update: Option<Instant>
 }
 
 impl MasterWindowState {
 
     pub async fn new(window: Window) -> MasterWindowState {
+        let renderer = WgpuRenderer::new(&window).await;
+        let scale_factor = window.scale_factor();
+        let mut state = Self::assemble(Some(window), Box::new(renderer));
+        state.rescale(scale_factor, state.size);
+        state.update_title();
+        state
+    }
+
+    pub async fn headless(width: u32, height: u32) -> MasterWindowState {
+        match Self::offscreen(width, height).await {
+            Some(state) => state,
+            // no adapter of any kind => rasterize on the CPU instead
+            None => Self::software(width, height)
+        }
+    }
 
-        let size = window.inner_size();
-        
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            // wgpu::Backends::all() => Vulkan + Metal + DX12 + WebGPU
-            backends: wgpu::Backends::all(),
-            // default shader compiler => naga
-            dx12_shader_compiler: Default::default()
-        });
+    // drawn by wgpu without a window, if there is an adapter to draw with
+    pub async fn offscreen(width: u32, height: u32) -> Option<MasterWindowState> {
+        let renderer = WgpuRenderer::offscreen(width, height).await?;
+        Some(Self::assemble(None, Box::new(renderer)))
+    }
 
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-
-        let adapter = instance.request_adapter(
-            &wgpu::RequestAdapterOptions {
-                // HighPerformance => more performant device, but more power-hungry
-                power_preference: wgpu::PowerPreference::HighPerformance,
-                // Some(&surface) => matches `surface` to the GPU
-                compatible_surface: Some(&surface),
-                // false => forces rendering system to use the GPU and not a 
-                //          fallback system of any kind
-                force_fallback_adapter: false
-            }
-        ).await.unwrap();
-
-        let (device, queue) = adapter.request_device(
-            &wgpu::DeviceDescriptor {
-                // specifies extra features potentially available on the GPU
-                features: wgpu::Features::empty(),
-                // the general limits on the types of resources able to be requested
-                limits: wgpu::Limits::default(),
-                label: Some("Local GPU Device")
-            },
-            None
-        ).await.unwrap();
-
-        let surface_caps = surface.get_capabilities(&adapter);
-
-        let surface_format = surface_caps.formats.iter()
-                                                 .copied()
-                                                 .find(|f| !f.describe().srgb)
-                                                 .unwrap();
-
-        let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            present_mode: surface_caps.present_modes[0],
-            alpha_mode: surface_caps.alpha_modes[0],
-            view_formats: vec![]
-        };
+    pub fn software(width: u32, height: u32) -> MasterWindowState {
+        Self::assemble(None, Box::new(SoftwareRenderer::new(width, height)))
+    }
+
+    fn assemble(window: Option<Window>, mut renderer: Box<dyn Renderer>) -> MasterWindowState {
 
-        surface.configure(&device, &config);
+        let (width, height) = renderer.dimensions();
+        let size = winit::dpi::PhysicalSize::new(width, height);
 
-        let mut scenes = HashMap::with_capacity(4);
-        scenes.insert(SceneName::Home, vec![]);
-        scenes.insert(SceneName::RootPicker, vec![]);
-        scenes.insert(SceneName::Grapher, vec![]);
-        scenes.insert(SceneName::Simulation, vec![]);
+        let mut scenes = HashMap::with_capacity(5);
+        scenes.insert(SceneName::Home, Scene::new(false));
+        scenes.insert(SceneName::RootPicker, Scene::new(false));
+        scenes.insert(SceneName::Grapher, Scene::new(true));
+        scenes.insert(SceneName::PhasePortrait, Scene::new(true));
+        scenes.insert(SceneName::Simulation, Scene::new(true));
 
         let mouse_position = MousePosition::new(0.0, 0.0, size.width.into(), size.height.into());
 
+        let newton_shader = renderer.register_shader("Newton Fractal", newton::SHADER);
+        renderer.register_fallback(newton_shader, newton::shade);
+
+        let mut typesetter = Typesetter::new();
+        let glyphs = renderer.register_texture(typesetter.atlas().image());
+        typesetter.atlas_mut().take_changes();
+
         Self {
-            window,
-            surface,
-            device,
-            queue,
-            config,
+            renderer,
             size,
+            scale_factor: 1.0,
+            window,
             cur_scene: SceneName::Home,
             buttons: vec![],
+            sliders: vec![],
             scenes,
-            mouse_position
+            mouse_position,
+            dragging: None,
+            grapher: Grapher::new(),
+            phase_portrait: PhasePortrait::new(),
+            root_picker: RootPicker::new(),
+            newton_shader,
+            simulation: Simulation::new(),
+            typesetter,
+            glyphs,
+            last_update: None
         }
 
     }
 
-    pub fn add_button(&mut self, scene: &SceneName, shape: &ShapeKind, vertices: Vec<Vertex>, scene_request: SceneName) {
+    // `action` is a SceneName for buttons that only navigate
+    pub fn add_button<A: Into<ButtonAction>>(&mut self, scene: &SceneName, shape: &ShapeKind, vertices: Vec<Vertex>, action: A) -> ButtonId {
 
         let entity = EntityBuilder::from_shape(
             *shape,
             vertices,
-        ).unwrap().build(
-            &self.device,
-            &self.config,
-            self.size.width,
-            self.size.height
-        );
+        ).unwrap().build();
 
         let button = Button::new(
             *scene,
-            scene_request,
+            action.into(),
+            *shape,
             entity
         );
 
         self.buttons.push(button);
+        ButtonId(self.buttons.len() - 1)
     }
 
-    pub fn next_scene(&self) -> SceneName {
-        match self.cur_scene {
-            SceneName::Home => SceneName::RootPicker,
-            SceneName::RootPicker => SceneName::Grapher,
-            SceneName::Grapher => SceneName::Simulation,
-            SceneName::Simulation => SceneName::Home
+    pub fn button(&self, id: ButtonId) -> &Button {
+        &self.buttons[id.0]
+    }
+
+    pub fn button_mut(&mut self, id: ButtonId) -> &mut Button {
+        &mut self.buttons[id.0]
+    }
+
+    // the topmost of the current scene's buttons under `point`, in canvas
+    // coordinates: the highest layer's, and of those the last added
+    fn button_at(&self, point: [f32; 2]) -> Option<usize> {
+        self.buttons.iter()
+                    .enumerate()
+                    .filter(|(_, button)| button.scene() == self.cur_scene && button.contains(point))
+                    .max_by_key(|&(i, button)| (button.layer(), i))
+                    .map(|(i, _)| i)
+    }
+
+    fn activate(&mut self, index: usize) {
+        let Some(mut action) = self.buttons[index].take_action() else {
+            return;
+        };
+        match &mut action {
+            ButtonAction::Navigate(scene) => self.set_scene(*scene),
+            ButtonAction::Run(run) => run(self)
         }
+        self.buttons[index].return_action(action);
+        // whatever ran may have changed what the title reads out
+        self.update_title();
     }
 
-    pub fn previous_scene(&self) -> SceneName {
-        match self.cur_scene {
-            SceneName::Home => SceneName::Home,
-            SceneName::RootPicker => SceneName::Home,
-            SceneName::Grapher => SceneName::RootPicker,
-            SceneName::Simulation => SceneName::Grapher
+    pub fn set_scene(&mut self, scene: SceneName) {
+        self.cur_scene = scene;
+        self.hover_buttons();
+        self.update_title();
+    }
+
+    pub fn current_scene(&self) -> SceneName {
+        self.cur_scene
+    }
+
+    fn hover_buttons(&mut self) {
+        let hovered = self.button_at(self.mouse_position.canvas());
+        for (i, button) in self.buttons.iter_mut().enumerate() {
+            button.set_hovered(hovered == Some(i));
         }
     }
 
+    pub fn add_slider(&mut self, slider: Slider) -> SliderId {
+        self.sliders.push(slider);
+        SliderId(self.sliders.len() - 1)
+    }
+
+    pub fn slider(&self, id: SliderId) -> &Slider {
+        &self.sliders[id.0]
+    }
+
+    pub fn slider_mut(&mut self, id: SliderId) -> &mut Slider {
+        &mut self.sliders[id.0]
+    }
+
+    // the last added of the current scene's sliders under `point`, in canvas
+    // coordinates
+    fn slider_at(&self, point: [f32; 2]) -> Option<usize> {
+        self.sliders.iter().rposition(|slider| slider.scene() == self.cur_scene && slider.contains(point))
+    }
+
+    // moves slider `index` to `value`, calling back if that changed it
+    fn slide(&mut self, index: usize, value: f64) {
+        if !self.sliders[index].set_value(value) {
+            return;
+        }
+        if let Some(mut on_change) = self.sliders[index].take_on_change() {
+            let value = self.sliders[index].value();
+            on_change(self, value);
+            self.sliders[index].return_on_change(on_change);
+        }
+    }
+
+    // keys go to the slider clicked last, while its scene is showing
+    fn focused_slider(&self) -> Option<usize> {
+        self.sliders.iter().position(|slider| slider.is_focused() && slider.scene() == self.cur_scene)
+    }
+
+    pub fn next_scene(&self) -> SceneName {
+        self.cur_scene.next()
+    }
+
+    pub fn previous_scene(&self) -> SceneName {
+        self.cur_scene.previous()
+    }
+
+    pub fn renderer(&self) -> &dyn Renderer {
+        self.renderer.as_ref()
+    }
+
+    // compiles `source` once; entities opt into it through `EntityBuilder::shader`
+    pub fn register_shader(&mut self, label: &str, source: &str) -> ShaderId {
+        self.renderer.register_shader(label, source)
+    }
+
     pub fn add_entity(&mut self, scene: &SceneName, entity: Entity) {
-        self.scenes.get_mut(scene).unwrap().push(entity);
+        self.scenes.get_mut(scene).unwrap().entities.push(entity);
     }
 
     pub fn add_shape(&mut self, scene: &SceneName, kind: &ShapeKind, vertices: Vec<Vertex>) {
-        self.scenes.get_mut(scene).unwrap().push(
+        self.scenes.get_mut(scene).unwrap().entities.push(
             EntityBuilder::from_shape(
                 *kind,
                 vertices
-            ).unwrap().build(
-                &self.device, 
-                &self.config, 
-                self.size.width,
-                self.size.height
-            )
+            ).unwrap().build()
         )
     }
 
+    // the stroke's width stays in pixels however far the scene is zoomed
+    pub fn add_line(&mut self, scene: &SceneName, vertices: Vec<Vertex>, stroke: &Stroke) {
+        let registered = self.scenes.get_mut(scene).unwrap();
+        registered.entities.push(
+            EntityBuilder::from_stroke(
+                vertices.clone(),
+                stroke,
+                registered.lines_zoom
+            ).unwrap().build()
+        );
+        registered.lines.push((registered.entities.len() - 1, vertices, stroke.clone()));
+    }
+
+    // `anchor` is where the top of the text meets its alignment edge
+    pub fn add_text(&mut self, scene: &SceneName, text: &Text, anchor: [f32; 2]) {
+        if let Some(entity) = self.typesetter.entity(text, anchor, self.glyphs) {
+            self.scenes.get_mut(scene).unwrap().entities.push(entity);
+        }
+    }
+
+    pub fn typesetter(&self) -> &Typesetter {
+        &self.typesetter
+    }
+
+    pub fn typesetter_mut(&mut self) -> &mut Typesetter {
+        &mut self.typesetter
+    }
+
+    pub fn background(&self, scene: &SceneName) -> Background {
+        self.scenes.get(scene).unwrap().background
+    }
+
+    pub fn set_background(&mut self, scene: &SceneName, background: Background) {
+        self.scenes.get_mut(scene).unwrap().background = background;
+    }
+
     pub fn window(&self) -> &Window {
-        &self.window
+        self.window.as_ref().expect("headless state has no window")
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
         self.size = new_size;
-        self.config.width = new_size.width;
-        self.config.height = new_size.height;
-        self.surface.configure(&self.device, &self.config);
+        self.renderer.resize(new_size.width, new_size.height);
+        self.mouse_position.update_window_dimensions(new_size.width.into(), new_size.height.into());
+    }
+
+    pub fn rescale(&mut self, scale_factor: f64, new_size: winit::dpi::PhysicalSize<u32>) {
+        self.scale_factor = scale_factor;
+        self.mouse_position.update_scale_factor(scale_factor);
+        self.resize(new_size);
+    }
+
+    // canvas space is measured in logical pixels, so a resize or a change in DPI
+    // reveals more or less of the world instead of stretching it
+    pub fn screen_projection(&self) -> Transform {
+        let [width, height] = self.logical_size();
+        Transform::orthographic(width, height)
+    }
+
+    pub fn view_projection(&self, scene: &SceneName) -> Transform {
+        self.camera(scene).view().then(&self.screen_projection())
+    }
+
+    pub fn camera(&self, scene: &SceneName) -> &Camera {
+        &self.scenes.get(scene).unwrap().camera
+    }
+
+    pub fn camera_mut(&mut self, scene: &SceneName) -> &mut Camera {
+        &mut self.scenes.get_mut(scene).unwrap().camera
+    }
+
+    pub fn set_navigable(&mut self, scene: &SceneName, navigable: bool) {
+        self.scenes.get_mut(scene).unwrap().navigable = navigable;
+    }
+
+    pub fn mouse_position(&self) -> &MousePosition {
+        &self.mouse_position
     }
 
     pub fn input(&mut self, event: &WindowEvent) {
         match event {
             WindowEvent::CursorMoved { position, .. } => {
                 self.mouse_position.update_from_window_coords(position.x, position.y);
+                self.hover_buttons();
+                let (previous, current) = self.mouse_position.movement();
+                let current_scene = self.cur_scene;
+                match self.dragging {
+                    Some(Drag::Camera) => self.camera_mut(&current_scene).pan(previous, current),
+                    Some(Drag::Root(index)) => {
+                        let point = self.camera(&current_scene).canvas_to_world(current);
+                        let z = self.root_picker.world_to_plane(point);
+                        self.root_picker.move_root(index, z);
+                        self.update_title();
+                    },
+                    Some(Drag::Slider(index)) => {
+                        let value = self.sliders[index].value_at(current[0]);
+                        self.slide(index, value);
+                    },
+                    None => {}
+                }
             },
             WindowEvent::MouseInput { state: ElementState::Pressed, button, .. } => {
                 if *button != MouseButton::Left {
                     return;
                 }
-                let current_scene = self.cur_scene;
-                for button in self.buttons.iter().filter(|b| b.inhabiting_scene == current_scene) {
-                    if self.mouse_position.between(button.left_bound(), button.right_bound(), button.bottom_bound(), button.top_bound()) {
-                        self.cur_scene = button.scene_request;
+                self.mouse_position.press();
+                let point = self.mouse_position.canvas();
+                let slider = self.slider_at(point);
+                for (i, other) in self.sliders.iter_mut().enumerate() {
+                    other.set_focused(slider == Some(i));
+                }
+                // even disabled buttons keep the press from reaching the scene
+                if let Some(index) = self.button_at(point) {
+                    self.buttons[index].press();
+                    self.dragging = None;
+                } else if let Some(index) = slider {
+                    // the thumb jumps to wherever the track was pressed
+                    self.dragging = Some(Drag::Slider(index));
+                    self.slide(index, self.sliders[index].value_at(point[0]));
+                } else {
+                    self.dragging = self.grab(self.cur_scene);
+                }
+            },
+            WindowEvent::MouseInput { state: ElementState::Released, button: MouseButton::Left, .. } => {
+                let pressed_at = self.mouse_position.release();
+                let clicked = pressed_at.is_some_and(|[x, y]| {
+                    (x - self.mouse_position.canvas_x() as f32).hypot(y - self.mouse_position.canvas_y() as f32) <= CLICK_DISTANCE
+                });
+                // clicking the phase plane, rather than dragging it, starts a trajectory there
+                if clicked && self.cur_scene == SceneName::PhasePortrait && self.dragging == Some(Drag::Camera) {
+                    let (x, y) = self.mouse_position.world_coords(self.camera(&SceneName::PhasePortrait));
+                    let start = self.phase_portrait.world_to_graph([x as f32, y as f32]);
+                    self.phase_portrait.add_trajectory(start);
+                }
+                self.dragging = None;
+                // a button acts once it is released over itself
+                if let Some(index) = self.buttons.iter().position(Button::is_pressed) {
+                    self.buttons[index].release();
+                    if self.button_at(self.mouse_position.canvas()) == Some(index) {
+                        self.activate(index);
                     }
                 }
+                self.hover_buttons();
+            },
+            // the arrow keys, Page Up and Down, Home and End move the focused slider
+            WindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(key), .. }, .. }
+                if self.focused_slider().is_some_and(|index| self.sliders[index].nudge(*key).is_some()) => {
+                let index = self.focused_slider().unwrap();
+                let value = self.sliders[index].nudge(*key).unwrap();
+                self.slide(index, value);
+            },
+            // C clears the phase portrait's trajectories
+            WindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(VirtualKeyCode::C), .. }, .. }
+                if self.cur_scene == SceneName::PhasePortrait => {
+                self.phase_portrait.clear_trajectories();
+            },
+            // F toggles the Newton fractal behind the roots
+            WindowEvent::KeyboardInput { input: KeyboardInput { state: ElementState::Pressed, virtual_keycode: Some(VirtualKeyCode::F), .. }, .. }
+                if self.cur_scene == SceneName::RootPicker => {
+                let shown = self.root_picker.fractal().is_some();
+                self.show_newton_fractal(!shown);
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                let current_scene = self.cur_scene;
+                if !self.scenes.get(&current_scene).unwrap().navigable {
+                    return;
+                }
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_SCROLL_LINE) as f32
+                };
+                let anchor = self.mouse_position.canvas();
+                self.camera_mut(&current_scene).zoom_about(ZOOM_PER_SCROLL_LINE.powf(lines), anchor);
             }
             _ => {}
         }
     }
 
-    pub fn update(&mut self) {
-        
+    // whatever sits under the cursor in `scene`: a root marker, or else the camera
+    // of a navigable scene
+    fn grab(&self, scene: SceneName) -> Option<Drag> {
+        let camera = self.camera(&scene);
+        let point = camera.canvas_to_world(self.mouse_position.canvas());
+        if scene == SceneName::RootPicker {
+            if let Some(index) = self.root_picker.marker_at(point, camera.zoom()) {
+                return Some(Drag::Root(index));
+            }
+        }
+        self.scenes.get(&scene).unwrap().navigable.then_some(Drag::Camera)
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    // the window title names the scene, and in the RootPicker reads out the
+    // polynomial's coefficients as its roots move
+    fn update_title(&self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+        let name: String = self.cur_scene.into();
+        match (self.cur_scene, self.root_picker.polynomial()) {
+            (SceneName::RootPicker, Some(polynomial)) => window.set_title(&format!("{}: p(z) = {:.3}", name, polynomial)),
+            _ => window.set_title(&name)
+        }
+    }
 
-        let output = self.surface.get_current_texture()?;
-        let view = output.texture.create_view(&wgpu::TextureViewDescriptor::default());
+    // the simulation only runs while its scene is on screen
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        let elapsed = self.last_update.map_or(0.0, |last| (now - last).as_secs_f64());
+        self.last_update = Some(now);
+        if self.cur_scene == SceneName::Simulation {
+            self.simulation.advance(elapsed);
+        }
+        self.refresh_generated(self.cur_scene);
+    }
 
-        let registered_entities = self.scenes.get(&self.cur_scene).unwrap();
+    pub fn grapher(&self) -> &Grapher {
+        &self.grapher
+    }
 
-        for entity in registered_entities {
+    pub fn grapher_mut(&mut self) -> &mut Grapher {
+        &mut self.grapher
+    }
 
-            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder")
-            });
+    pub fn phase_portrait(&self) -> &PhasePortrait {
+        &self.phase_portrait
+    }
 
-            {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Load,
-                            store: true
-                        }
-                    })],
-                    depth_stencil_attachment: None
-                });
+    pub fn phase_portrait_mut(&mut self) -> &mut PhasePortrait {
+        &mut self.phase_portrait
+    }
 
-                render_pass.set_pipeline(entity.pipeline());
-                // println!("{}", entity.num_vertices());
-                render_pass.set_vertex_buffer(0, entity.vertices().slice(..));
-                render_pass.draw(0..entity.num_vertices(), 0..1);
+    pub fn root_picker(&self) -> &RootPicker {
+        &self.root_picker
+    }
 
-            }
+    pub fn root_picker_mut(&mut self) -> &mut RootPicker {
+        &mut self.root_picker
+    }
 
-            self.queue.submit(std::iter::once(encoder.finish()));
+    pub fn simulation(&self) -> &Simulation {
+        &self.simulation
+    }
 
-        }
+    pub fn simulation_mut(&mut self) -> &mut Simulation {
+        &mut self.simulation
+    }
 
-        let button_entities = self.buttons.iter().map(|b| &b.entity);
+    // fills the RootPicker's plane with the basins of attraction of its roots
+    pub fn show_newton_fractal(&mut self, shown: bool) {
+        let shader = shown.then_some(self.newton_shader);
+        self.root_picker.set_fractal(shader);
+    }
 
-        for (button, entity) in self.buttons.iter().zip(button_entities) {
+    fn logical_size(&self) -> [f32; 2] {
+        [
+            (self.size.width as f64 / self.scale_factor) as f32,
+            (self.size.height as f64 / self.scale_factor) as f32
+        ]
+    }
 
-            if button.inhabiting_scene != self.cur_scene {
-                continue;
+    // brings view-dependent entities up to date with the scene's camera
+    fn refresh_generated(&mut self, scene: SceneName) {
+        let camera = *self.camera(&scene);
+        let size = self.logical_size();
+        let replotted = match scene {
+            SceneName::Grapher => self.grapher.replot(&camera, size),
+            SceneName::PhasePortrait => self.phase_portrait.replot(&camera, size),
+            SceneName::RootPicker => self.root_picker.replot(&camera, size),
+            SceneName::Simulation => {
+                self.simulation.redraw(&mut self.scenes.get_mut(&scene).unwrap().generated);
+                None
             }
-
-            let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder")
-            });
-
-            {
-                let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                    label: Some("Render Pass"),
-                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                        view: &view,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Load,
-                            store: true
-                        }
-                    })],
-                    depth_stencil_attachment: None
+            _ => None
+        };
+        let registered = self.scenes.get_mut(&scene).unwrap();
+        let changed = replotted.is_some();
+        if let Some(entities) = replotted {
+            registered.generated = entities;
+        }
+        if registered.lines_zoom != camera.zoom() {
+            registered.lines_zoom = camera.zoom();
+            for (index, vertices, stroke) in &registered.lines {
+                registered.entities[*index] = EntityBuilder::from_stroke(vertices.clone(), stroke, camera.zoom()).unwrap().build();
+            }
+        }
+        if scene == SceneName::RootPicker {
+            registered.parameters = self.root_picker.parameters();
+            // the roots only move between replots, so neither does the polynomial
+            if changed {
+                registered.readout = self.root_picker.polynomial().and_then(|polynomial| {
+                    let text = Text::new(&format!("p(z) = {:.3}", polynomial), 18.0).max_width(size[0] - 24.0).align(Align::Center);
+                    self.typesetter.entity(&text, [0.0, size[1] / 2.0 - 12.0], self.glyphs)
                 });
-
-                render_pass.set_pipeline(entity.pipeline());
-                // println!("{}", entity.num_vertices());
-                render_pass.set_vertex_buffer(0, entity.vertices().slice(..));
-                render_pass.draw(0..entity.num_vertices(), 0..1);
-
             }
+        }
+    }
 
-            self.queue.submit(std::iter::once(encoder.finish()));
+    // typesets button labels and tooltips and slider labels, then uploads glyphs rasterized
+    // since the last frame
+    fn sync_glyphs(&mut self) {
+        for button in &mut self.buttons {
+            button.typeset(&mut self.typesetter, self.glyphs);
+        }
+        for slider in &mut self.sliders {
+            slider.typeset(&mut self.typesetter, self.glyphs);
+        }
+        if let Some(atlas) = self.typesetter.atlas_mut().take_changes() {
+            self.renderer.update_texture(self.glyphs, atlas);
+        }
+    }
 
+    fn frame<'a>(scenes: &'a HashMap<SceneName, Scene>, buttons: &'a [Button], sliders: &'a [Slider], scene: SceneName, screen_projection: Transform) -> Frame<'a> {
+        let registered = scenes.get(&scene).unwrap();
+        // bottom layer first; the sort is stable, so later buttons go on top
+        let mut buttons: Vec<&Button> = buttons.iter().filter(|b| b.scene() == scene).collect();
+        buttons.sort_by_key(|b| b.layer());
+        Frame {
+            background: registered.background,
+            view_projection: registered.camera.view().then(&screen_projection),
+            screen_projection,
+            entities: registered.entities.iter().chain(&registered.generated).collect(),
+            // readouts, buttons and sliders stay put while the camera moves, and tooltips
+            // cover them all
+            overlay: registered.readout.iter()
+                            .chain(buttons.iter().flat_map(|b| b.entities()))
+                            .chain(sliders.iter().filter(|s| s.scene() == scene).flat_map(Slider::entities))
+                            .chain(buttons.iter().flat_map(|b| b.tooltip()))
+                            .collect(),
+            parameters: &registered.parameters
         }
+    }
 
-        output.present();
+    pub fn render(&mut self) -> Result<(), RenderError> {
+        self.sync_glyphs();
+        let frame = Self::frame(&self.scenes, &self.buttons, &self.sliders, self.cur_scene, self.screen_projection());
+        self.renderer.render(&frame)
+    }
 
-        Ok(())
+    pub fn capture_scene(&mut self, scene: SceneName) -> Image {
+        self.refresh_generated(scene);
+        self.sync_glyphs();
+        let frame = Self::frame(&self.scenes, &self.buttons, &self.sliders, scene, self.screen_projection());
+        self.renderer.capture(&frame)
+    }
+
+    pub fn export_scene<P: AsRef<Path>>(&mut self, scene: SceneName, path: P) -> Result<(), ImageError> {
+        self.capture_scene(scene).save_png(path)
     }
 
     pub async fn run(mut self, event_loop: EventLoop<()>) {
@@ -459,15 +830,15 @@ impl MasterWindowState {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
                 WindowEvent::Resized(physical_size) => self.resize(physical_size),
-                WindowEvent::ScaleFactorChanged { new_inner_size, .. } => self.resize(*new_inner_size),
+                WindowEvent::ScaleFactorChanged { scale_factor, new_inner_size } => self.rescale(scale_factor, *new_inner_size),
                 _ => self.input(&event),
             },
             Event::RedrawRequested(_) => {
                 self.update();
                 match self.render() {
                     Ok(_) => {},
-                    Err(wgpu::SurfaceError::Lost) => self.resize(self.size),
-                    Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
+                    Err(RenderError::Surface(wgpu::SurfaceError::Lost)) => self.resize(self.size),
+                    Err(RenderError::Surface(wgpu::SurfaceError::OutOfMemory)) => *control_flow = ControlFlow::Exit,
                     Err(e) => eprintln!("{:?}", e)
                 }
             },
@@ -480,3 +851,109 @@ impl MasterWindowState {
 
 
 
+
+// feeds the handlers the events the window would send for input at canvas
+// points, so tests can click and type the way users do
+#[cfg(test)]
+impl MasterWindowState {
+
+    pub fn move_cursor(&mut self, [x, y]: [f64; 2]) {
+        let position = winit::dpi::PhysicalPosition::new(
+            x * self.scale_factor + self.size.width as f64 / 2.0,
+            self.size.height as f64 / 2.0 - y * self.scale_factor
+        );
+        #[allow(deprecated)]
+        self.input(&WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position,
+            modifiers: ModifiersState::empty()
+        });
+    }
+
+    pub fn mouse_button(&mut self, state: ElementState) {
+        #[allow(deprecated)]
+        self.input(&WindowEvent::MouseInput {
+            device_id: unsafe { DeviceId::dummy() },
+            state,
+            button: MouseButton::Left,
+            modifiers: ModifiersState::empty()
+        });
+    }
+
+    pub fn click(&mut self, point: [f64; 2]) {
+        self.move_cursor(point);
+        self.mouse_button(ElementState::Pressed);
+        self.mouse_button(ElementState::Released);
+    }
+
+    pub fn press_key(&mut self, key: VirtualKeyCode) {
+        #[allow(deprecated)]
+        self.input(&WindowEvent::KeyboardInput {
+            device_id: unsafe { DeviceId::dummy() },
+            input: KeyboardInput {
+                scancode: 0,
+                state: ElementState::Pressed,
+                virtual_keycode: Some(key),
+                modifiers: ModifiersState::empty()
+            },
+            is_synthetic: false
+        });
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::colors::WHITE;
+    use crate::complex::Complex;
+    use crate::roots::Polynomial;
+
+    #[test]
+    fn mouse_positions_are_logical_pixels_from_the_middle() {
+        let mut mouse = MousePosition::new(0.0, 0.0, 400.0, 300.0);
+        mouse.update_scale_factor(2.0);
+        mouse.update_from_window_coords(300.0, 50.0);
+        assert_eq!(mouse.canvas(), [50.0, 50.0]);
+        mouse.update_from_canvas_coords(-20.0, 10.0);
+        assert_eq!(mouse.canvas(), [-20.0, 10.0]);
+        mouse.update_window_dimensions(800.0, 600.0);
+        mouse.update_from_window_coords(400.0, 300.0);
+        assert_eq!(mouse.canvas(), [0.0, 0.0]);
+    }
+
+    #[test]
+    fn buttons_stay_under_the_cursor_after_resizing() {
+        let mut state = MasterWindowState::software(160, 120);
+        state.add_button(&SceneName::Home, &ShapeKind::Circle(10.0), vec![Vertex::new(50.0, 10.0, 0.0, WHITE)], SceneName::Grapher);
+        state.rescale(2.0, winit::dpi::PhysicalSize::new(400, 300));
+        // physical pixels from the top left, twice the logical distance from the middle
+        #[allow(deprecated)]
+        state.input(&WindowEvent::CursorMoved {
+            device_id: unsafe { DeviceId::dummy() },
+            position: winit::dpi::PhysicalPosition::new(200.0 + 100.0, 150.0 - 20.0),
+            modifiers: ModifiersState::empty()
+        });
+        state.mouse_button(ElementState::Pressed);
+        state.mouse_button(ElementState::Released);
+        assert!(state.current_scene() == SceneName::Grapher);
+    }
+
+    #[test]
+    fn the_root_picker_reads_out_its_polynomial() {
+        let mut state = MasterWindowState::software(400, 300);
+        state.refresh_generated(SceneName::RootPicker);
+        assert!(state.scenes[&SceneName::RootPicker].readout.is_none());
+        // z^3 - 1
+        state.root_picker_mut().set_polynomial(Polynomial::from_real(&[-1.0, 0.0, 0.0, 1.0])).unwrap();
+        state.refresh_generated(SceneName::RootPicker);
+        let glyphs = |state: &MasterWindowState| state.scenes[&SceneName::RootPicker].readout.as_ref().unwrap().vertices.len();
+        let before = glyphs(&state);
+        state.root_picker_mut().move_root(0, Complex::new(2.0, 0.5));
+        state.refresh_generated(SceneName::RootPicker);
+        assert!(glyphs(&state) != before);
+    }
+
+}
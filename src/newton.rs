@@ -0,0 +1,106 @@
+use crate::colors::Color;
+use crate::complex::Complex;
+use crate::pipeline::MAX_PARAMETERS;
+use crate::roots::Polynomial;
+
+pub const SHADER: &str = include_str!("newton.wgsl");
+
+pub const MAX_DEGREE: usize = (MAX_PARAMETERS - 2) / 3;
+
+// these have to match newton.wgsl
+const MAX_STEPS: usize = 64;
+const CAPTURE: f32 = 1e-6;
+const DARKENING_PER_STEP: f32 = 0.08;
+
+// the degree and scale, then the coefficients, roots and root colors
+pub fn parameters(polynomial: &Polynomial, roots: &[Complex], scale: f32, color: fn(usize) -> Color) -> Option<Vec<[f32; 4]>> {
+
+    let degree = polynomial.degree();
+    if degree > MAX_DEGREE || roots.len() != degree {
+        return None;
+    }
+
+    let mut parameters = vec![[degree as f32, scale, 0.0, 0.0]];
+    let complex = |z: &Complex| [z.re as f32, z.im as f32, 0.0, 0.0];
+    parameters.extend(polynomial.coefficients().iter().map(complex));
+    parameters.extend(roots.iter().map(complex));
+    parameters.extend((0..degree).map(|k| {
+        let [r, g, b] = color(k).in_percentages();
+        [r, g, b, 0.0]
+    }));
+    Some(parameters)
+}
+
+// newton.wgsl's fragment shader on the CPU, step for step in single precision
+// so both renderers agree on which basin a pixel falls into
+pub fn shade(position: [f32; 2], _color: [f32; 3], parameters: &[[f32; 4]]) -> [f32; 3] {
+
+    let Some(&[degree, scale, _, _]) = parameters.first() else {
+        return [0.0; 3];
+    };
+    let degree = degree as usize;
+    let xy = |index: usize| [parameters[index][0], parameters[index][1]];
+
+    let mut z = [position[0] / scale, position[1] / scale];
+    for step in 0..MAX_STEPS {
+        let mut value = [0.0, 0.0];
+        let mut slope = [0.0, 0.0];
+        for i in (0..=degree).rev() {
+            slope = add(multiply(slope, z), value);
+            value = add(multiply(value, z), xy(1 + i));
+        }
+        for k in 0..degree {
+            let [dx, dy] = [z[0] - xy(2 + degree + k)[0], z[1] - xy(2 + degree + k)[1]];
+            if dx * dx + dy * dy < CAPTURE {
+                let shade = 1.0 / (1.0 + DARKENING_PER_STEP * step as f32);
+                let [r, g, b, _] = parameters[2 + 2 * degree + k];
+                return [r * shade, g * shade, b * shade];
+            }
+        }
+        if slope[0] * slope[0] + slope[1] * slope[1] == 0.0 {
+            break;
+        }
+        let [dx, dy] = divide(value, slope);
+        z = [z[0] - dx, z[1] - dy];
+    }
+    [0.0; 3]
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn multiply(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] * b[0] - a[1] * b[1], a[0] * b[1] + a[1] * b[0]]
+}
+
+fn divide(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    let norm_squared = b[0] * b[0] + b[1] * b[1];
+    [(a[0] * b[0] + a[1] * b[1]) / norm_squared, (a[1] * b[0] - a[0] * b[1]) / norm_squared]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::colors::{BLUE, RED};
+
+    #[test]
+    fn points_fall_into_the_nearest_basin() {
+        // z^2 - 1, whose basins are split by the imaginary axis
+        let polynomial = Polynomial::from_real(&[-1.0, 0.0, 1.0]);
+        let roots = [Complex::from(-1.0), Complex::from(1.0)];
+        let parameters = parameters(&polynomial, &roots, 100.0, |k| [RED, BLUE][k]).unwrap();
+
+        let [red, blue] = [RED, BLUE].map(|color| color.in_percentages());
+        assert_eq!(shade([-100.0, 0.0], [0.0; 3], &parameters), red);
+        assert_eq!(shade([100.0, 0.0], [0.0; 3], &parameters), blue);
+        // further out takes more steps, so comes out darker
+        let far = shade([250.0, 40.0], [0.0; 3], &parameters);
+        assert!(far[2] > 0.0 && far[2] < blue[2]);
+        assert!((far[0] / far[2] - blue[0] / blue[2]).abs() < 1e-6);
+        // Newton's method stalls on the axis itself, where p' is parallel to it
+        assert_eq!(shade([0.0, 50.0], [0.0; 3], &parameters), [0.0; 3]);
+    }
+
+}
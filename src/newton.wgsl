@@ -0,0 +1,66 @@
+// Newton fractal: every point of the complex plane is colored by the root
+// Newton's method reaches from it, darker the more steps that takes. The
+// parameters are packed by newton.rs, which also mirrors this on the CPU.
+struct Globals {
+	view_projection: mat4x4<f32>,
+	// [0] => degree, world units per plane unit
+	// then degree + 1 coefficients, lowest first, degree roots and their colors
+	parameters: array<vec4<f32>, 64>
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+struct VertexIn {
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec3<f32>
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4<f32>,
+	@location(0) world: vec2<f32>
+}
+
+@vertex
+fn vertex_shader_main(model: VertexIn) -> VertexOut {
+	var out: VertexOut;
+	out.position = globals.view_projection * vec4<f32>(model.position, 1.0);
+	out.world = model.position.xy;
+	return out;
+}
+
+fn multiply(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+	return vec2<f32>(a.x * b.x - a.y * b.y, a.x * b.y + a.y * b.x);
+}
+
+fn divide(a: vec2<f32>, b: vec2<f32>) -> vec2<f32> {
+	return vec2<f32>(a.x * b.x + a.y * b.y, a.y * b.x - a.x * b.y) / dot(b, b);
+}
+
+@fragment
+fn fragment_shader_main(in: VertexOut) -> @location(0) vec4<f32> {
+	let degree = i32(globals.parameters[0].x);
+	var z = in.world / globals.parameters[0].y;
+	for (var step = 0; step < 64; step = step + 1) {
+		// p(z) and p'(z) together by Horner's scheme
+		var value = vec2<f32>(0.0, 0.0);
+		var slope = vec2<f32>(0.0, 0.0);
+		for (var i = degree; i >= 0; i = i - 1) {
+			slope = multiply(slope, z) + value;
+			value = multiply(value, z) + globals.parameters[1 + i].xy;
+		}
+		for (var k = 0; k < degree; k = k + 1) {
+			let offset = z - globals.parameters[2 + degree + k].xy;
+			if dot(offset, offset) < 1e-6 {
+				let shade = 1.0 / (1.0 + 0.08 * f32(step));
+				return vec4<f32>(globals.parameters[2 + 2 * degree + k].xyz * shade, 1.0);
+			}
+		}
+		if dot(slope, slope) == 0.0 {
+			break;
+		}
+		z = z - divide(value, slope);
+	}
+	// never settled on a root
+	return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,306 @@
+use crate::colors::{Color, WHITE};
+use crate::entities::{Entity, EntityBuilder, Instance, ShapeKind, Vertex};
+use crate::simulation::Simulated;
+
+// corners of the polygon every particle is drawn as
+const TEMPLATE_SEGMENTS: usize = 12;
+const DEFAULT_CAPACITY: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: [f32; 2],
+    pub velocity: [f32; 2],
+    pub color: Color,
+    // seconds since it was spawned
+    pub age: f32,
+    // seconds it lives for, f32::INFINITY for ever
+    pub lifetime: f32
+}
+
+impl Particle {
+
+    pub fn new(position: [f32; 2], velocity: [f32; 2], color: Color, lifetime: f32) -> Particle {
+        Self { position, velocity, color, age: 0.0, lifetime }
+    }
+
+    pub fn alive(&self) -> bool {
+        self.age < self.lifetime
+    }
+
+    // the share of its life left, which particles shrink with
+    pub fn remaining(&self) -> f32 {
+        if self.lifetime.is_finite() {
+            (1.0 - self.age / self.lifetime).max(0.0)
+        } else {
+            1.0
+        }
+    }
+
+}
+
+// Spawns `rate` particles per second at `origin`, heading up to `spread`
+// radians either side of `direction`, with speeds, lifetimes and colors drawn
+// at random from the given ranges.
+#[derive(Clone, PartialEq)]
+pub struct Emitter {
+    origin: [f32; 2],
+    rate: f32,
+    direction: f32,
+    spread: f32,
+    speed: (f32, f32),
+    lifetime: (f32, f32),
+    colors: Vec<Color>
+}
+
+impl Emitter {
+
+    pub fn new(origin: [f32; 2], rate: f32) -> Emitter {
+        Self {
+            origin,
+            rate,
+            direction: std::f32::consts::FRAC_PI_2,
+            spread: std::f32::consts::PI,
+            speed: (50.0, 100.0),
+            lifetime: (1.0, 2.0),
+            colors: vec![WHITE]
+        }
+    }
+
+    pub fn direction(mut self, direction: f32, spread: f32) -> Emitter {
+        self.direction = direction;
+        self.spread = spread;
+        self
+    }
+
+    pub fn speed(mut self, min: f32, max: f32) -> Emitter {
+        self.speed = (min, max);
+        self
+    }
+
+    pub fn lifetime(mut self, min: f32, max: f32) -> Emitter {
+        self.lifetime = (min, max);
+        self
+    }
+
+    // an empty list keeps the colors it had
+    pub fn colors(mut self, colors: Vec<Color>) -> Emitter {
+        if !colors.is_empty() {
+            self.colors = colors;
+        }
+        self
+    }
+
+    pub fn set_origin(&mut self, origin: [f32; 2]) {
+        self.origin = origin;
+    }
+
+}
+
+// xorshift64*, enough to scatter particles and reproducible between runs
+struct Random(u64);
+
+impl Random {
+
+    // uniform in [0, 1)
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn between(&mut self, (min, max): (f32, f32)) -> f32 {
+        min + (max - min) * self.next()
+    }
+
+}
+
+// Moves many particles under a shared constant acceleration, removing them as
+// they expire. All of them are drawn with one instanced entity, a small polygon
+// scaled to `radius` as they are born and shrinking away over their lifetime.
+pub struct ParticleSystem {
+    particles: Vec<Particle>,
+    radius: f32,
+    acceleration: [f32; 2],
+    emitter: Option<Emitter>,
+    // fraction of a particle the emitter still owes from earlier steps
+    owed: f32,
+    capacity: usize,
+    random: Random,
+    // of the polygon at unit radius
+    outline: Vec<Vertex>
+}
+
+impl ParticleSystem {
+
+    pub fn new(radius: f32) -> ParticleSystem {
+        let outline = (0..TEMPLATE_SEGMENTS).map(|i| {
+            let theta = std::f32::consts::TAU * i as f32 / TEMPLATE_SEGMENTS as f32;
+            Vertex::new(theta.cos(), theta.sin(), 0.0, WHITE)
+        }).collect();
+        Self {
+            particles: vec![],
+            radius,
+            acceleration: [0.0, 0.0],
+            emitter: None,
+            owed: 0.0,
+            capacity: DEFAULT_CAPACITY,
+            random: Random(0x9e37_79b9_7f4a_7c15),
+            outline
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn len(&self) -> usize {
+        self.particles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.particles.is_empty()
+    }
+
+    pub fn set_acceleration(&mut self, acceleration: [f32; 2]) {
+        self.acceleration = acceleration;
+    }
+
+    pub fn emitter_mut(&mut self) -> Option<&mut Emitter> {
+        self.emitter.as_mut()
+    }
+
+    pub fn set_emitter(&mut self, emitter: Option<Emitter>) {
+        self.emitter = emitter;
+        self.owed = 0.0;
+    }
+
+    // the most particles alive at once; spawning stops while it is reached
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+    }
+
+    pub fn spawn(&mut self, particle: Particle) {
+        if self.particles.len() < self.capacity {
+            self.particles.push(particle);
+        }
+    }
+
+    // semi-implicit Euler, then whatever the emitter owes for `dt`
+    pub fn update(&mut self, dt: f32) {
+
+        let [ax, ay] = self.acceleration;
+        for particle in &mut self.particles {
+            particle.velocity[0] += ax * dt;
+            particle.velocity[1] += ay * dt;
+            particle.position[0] += particle.velocity[0] * dt;
+            particle.position[1] += particle.velocity[1] * dt;
+            particle.age += dt;
+        }
+        self.particles.retain(Particle::alive);
+
+        let Some(emitter) = &self.emitter else {
+            return;
+        };
+        self.owed += emitter.rate * dt;
+        let due = self.owed.floor();
+        self.owed -= due;
+        for _ in 0..due as usize {
+            let angle = emitter.direction + emitter.spread * (2.0 * self.random.next() - 1.0);
+            let speed = self.random.between(emitter.speed);
+            let color = emitter.colors[(self.random.next() * emitter.colors.len() as f32) as usize];
+            let mut particle = Particle::new(
+                emitter.origin,
+                [speed * angle.cos(), speed * angle.sin()],
+                color,
+                self.random.between(emitter.lifetime)
+            );
+            // spread the batch over the step instead of bunching it at the origin
+            let head_start = dt * self.random.next();
+            particle.position[0] += particle.velocity[0] * head_start;
+            particle.position[1] += particle.velocity[1] * head_start;
+            particle.age = head_start;
+            if self.particles.len() < self.capacity {
+                self.particles.push(particle);
+            }
+        }
+    }
+
+    pub fn instances(&self) -> Vec<Instance> {
+        self.particles.iter().map(|particle| {
+            let [x, y] = particle.position;
+            Instance::new(x, y, self.radius * particle.remaining(), particle.color)
+        }).collect()
+    }
+
+    pub fn entity(&self) -> Entity {
+        EntityBuilder::from_shape(ShapeKind::Polygon, self.outline.clone())
+            .unwrap()
+            .instanced(self.instances())
+            .build()
+    }
+
+}
+
+impl Simulated for ParticleSystem {
+
+    fn step(&mut self, _t: f64, dt: f64) {
+        self.update(dt as f32);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        vec![self.entity()]
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn particles_fall_and_expire() {
+        let mut system = ParticleSystem::new(2.0);
+        system.set_acceleration([0.0, -10.0]);
+        system.spawn(Particle::new([0.0, 0.0], [5.0, 0.0], WHITE, 1.0));
+        system.spawn(Particle::new([0.0, 0.0], [0.0, 0.0], WHITE, f32::INFINITY));
+        for _ in 0..4 {
+            system.update(0.125);
+        }
+        let falling = system.particles()[0];
+        assert_eq!(falling.position[0], 2.5);
+        // semi-implicit Euler: (1 + 2 + 3 + 4) * 10 * 0.125^2
+        assert_eq!(falling.position[1], -1.5625);
+        assert_eq!(system.instances()[0].scale, 1.0);
+        for _ in 0..4 {
+            system.update(0.125);
+        }
+        assert_eq!(system.len(), 1);
+        assert_eq!(system.instances()[0].scale, 2.0);
+    }
+
+    #[test]
+    fn emitter_keeps_its_rate() {
+        let mut system = ParticleSystem::new(1.0);
+        system.set_emitter(Some(Emitter::new([0.0, 0.0], 20.0).lifetime(10.0, 10.0)));
+        for _ in 0..10 {
+            system.update(0.125);
+        }
+        // 2.5 per step, the halves carried over
+        assert_eq!(system.len(), 25);
+        system.set_capacity(40);
+        for _ in 0..10 {
+            system.update(0.125);
+        }
+        assert_eq!(system.len(), 40);
+    }
+
+    #[test]
+    fn emitters_need_a_color() {
+        let mut system = ParticleSystem::new(1.0);
+        system.set_emitter(Some(Emitter::new([0.0, 0.0], 8.0).colors(vec![])));
+        system.update(0.5);
+        assert_eq!(system.len(), 4);
+    }
+}
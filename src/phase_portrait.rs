@@ -0,0 +1,304 @@
+use hebrides::linal::Vector;
+
+use crate::camera::Camera;
+use crate::colors::{Color, WHITE};
+use crate::entities::{Entity, EntityBuilder, ShapeKind, Vertex};
+use crate::pipeline::PipelineKey;
+use crate::simulation::{rk4, State};
+use crate::stroke::{LineJoin, Stroke};
+
+const DEFAULT_SCALE: f64 = 50.0;
+// sizes in logical pixels, kept constant as the camera zooms
+const ARROW_SPACING: f32 = 32.0;
+const SHAFT_WIDTH: f32 = 2.0;
+const AXIS_WIDTH: f32 = 1.0;
+const TRAJECTORY_WIDTH: f32 = 2.0;
+const START_RADIUS: f32 = 4.0;
+const ARROW_FILL: f32 = 0.85;
+const HEAD_LENGTH: f32 = 0.35;
+const HEAD_WIDTH: f32 = 0.3;
+const WEAK_HUE: f32 = 240.0;
+const STRONG_HUE: f32 = 0.0;
+
+const MAX_STEP_LENGTH: f64 = 0.02;
+const MAX_STEP_TIME: f64 = 0.05;
+const MAX_STEPS: usize = 2000;
+const STALL_SPEED: f64 = 1e-6;
+const ESCAPE_DISTANCE: f64 = 1e3;
+
+type Point = [f64; 2];
+type Field = Box<dyn Fn(f64, f64) -> Point>;
+
+struct Trajectory {
+    start: Point,
+    // through `start`, from the past into the future
+    points: Vec<Point>
+}
+
+// A vector field F(x, y) drawn as arrows, with trajectories x' = F(x) through chosen points
+pub struct PhasePortrait {
+    field: Option<Field>,
+    trajectories: Vec<Trajectory>,
+    scale: f64,
+    axis_color: Color,
+    trajectory_color: Color,
+    // the camera and canvas size the last entities were plotted for
+    plotted: Option<(Camera, [f32; 2])>
+}
+
+impl PhasePortrait {
+
+    pub fn new() -> PhasePortrait {
+        Self {
+            field: None,
+            trajectories: vec![],
+            scale: DEFAULT_SCALE,
+            axis_color: Color::new(110, 110, 110),
+            trajectory_color: WHITE,
+            plotted: None
+        }
+    }
+
+    // trajectories already started follow the new field from the same points
+    pub fn set_field<F: Fn(f64, f64) -> Point + 'static>(&mut self, field: F) {
+        let field: Field = Box::new(field);
+        for trajectory in &mut self.trajectories {
+            trajectory.points = follow(&field, trajectory.start);
+        }
+        self.field = Some(field);
+        self.plotted = None;
+    }
+
+    pub fn add_trajectory(&mut self, start: Point) {
+        let points = self.field.as_ref().map_or_else(Vec::new, |field| follow(field, start));
+        self.trajectories.push(Trajectory { start, points });
+        self.plotted = None;
+    }
+
+    pub fn clear_trajectories(&mut self) {
+        self.trajectories.clear();
+        self.plotted = None;
+    }
+
+    pub fn trajectory(&self, index: usize) -> &[Point] {
+        &self.trajectories[index].points
+    }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f64) {
+        self.scale = scale;
+        self.plotted = None;
+    }
+
+    pub fn set_trajectory_color(&mut self, color: Color) {
+        self.trajectory_color = color;
+        self.plotted = None;
+    }
+
+    pub fn graph_to_world(&self, point: Point) -> [f32; 2] {
+        [(point[0] * self.scale) as f32, (point[1] * self.scale) as f32]
+    }
+
+    pub fn world_to_graph(&self, point: [f32; 2]) -> Point {
+        [point[0] as f64 / self.scale, point[1] as f64 / self.scale]
+    }
+
+    // new entities when the view has changed since the last call, None otherwise
+    pub fn replot(&mut self, camera: &Camera, canvas_size: [f32; 2]) -> Option<Vec<Entity>> {
+        if self.plotted == Some((*camera, canvas_size)) {
+            return None;
+        }
+        self.plotted = Some((*camera, canvas_size));
+        Some(self.entities(camera, canvas_size))
+    }
+
+    // the axes, then the arrows as one entity, then every trajectory with a
+    // dot where it was started
+    pub fn entities(&self, camera: &Camera, canvas_size: [f32; 2]) -> Vec<Entity> {
+
+        let (min, max) = camera.visible_region(canvas_size);
+        let pixel = 1.0 / camera.zoom();
+        let mut entities = vec![];
+
+        let axis = Stroke::new(AXIS_WIDTH);
+        let line = |points: &[[f32; 2]], color: Color, stroke: &Stroke| {
+            let vertices = points.iter().map(|&[x, y]| Vertex::new(x, y, 0.0, color)).collect();
+            EntityBuilder::from_stroke(vertices, stroke, camera.zoom()).ok().map(|builder| builder.build())
+        };
+        entities.extend(line(&[[min[0], 0.0], [max[0], 0.0]], self.axis_color, &axis));
+        entities.extend(line(&[[0.0, min[1]], [0.0, max[1]]], self.axis_color, &axis));
+
+        if let Some(field) = &self.field {
+            let arrows = self.arrows(field, min, max, pixel);
+            if !arrows.is_empty() {
+                entities.push(Entity::new(arrows, PipelineKey::default()));
+            }
+        }
+
+        let curve = Stroke::new(TRAJECTORY_WIDTH).join(LineJoin::Round);
+        for trajectory in &self.trajectories {
+            let points: Vec<[f32; 2]> = trajectory.points.iter().map(|&point| self.graph_to_world(point)).collect();
+            entities.extend(line(&points, self.trajectory_color, &curve));
+        }
+        for trajectory in &self.trajectories {
+            let [x, y] = self.graph_to_world(trajectory.start);
+            entities.push(
+                EntityBuilder::from_shape(ShapeKind::Circle(START_RADIUS * pixel), vec![Vertex::new(x, y, 0.0, self.trajectory_color)])
+                    .unwrap()
+                    .build()
+            );
+        }
+
+        entities
+    }
+
+    // triangles of one arrow per grid point in view; the grid is fixed to the
+    // plane so arrows don't slide around as the camera pans
+    fn arrows(&self, field: &Field, min: [f32; 2], max: [f32; 2], pixel: f32) -> Vec<Vertex> {
+
+        let spacing = ARROW_SPACING * pixel;
+        let cells = |low: f32, high: f32| (low / spacing).ceil() as i32..=(high / spacing).floor() as i32;
+        let samples: Vec<([f32; 2], Point)> = cells(min[0], max[0])
+            .flat_map(|i| cells(min[1], max[1]).map(move |j| [i as f32 * spacing, j as f32 * spacing]))
+            .map(|position| {
+                let [x, y] = self.world_to_graph(position);
+                (position, field(x, y))
+            })
+            .filter(|(_, value)| value[0].is_finite() && value[1].is_finite())
+            .collect();
+
+        let magnitude = |value: Point| (value[0] * value[0] + value[1] * value[1]).sqrt();
+        let strongest = samples.iter().map(|&(_, value)| magnitude(value)).fold(0.0, f64::max);
+        if strongest == 0.0 {
+            return vec![];
+        }
+
+        let mut vertices = vec![];
+        for (center, value) in samples {
+            let strength = (magnitude(value) / strongest) as f32;
+            if strength == 0.0 {
+                continue;
+            }
+            let direction = [(value[0] / magnitude(value)) as f32, (value[1] / magnitude(value)) as f32];
+            let color = Color::from_hsv(WEAK_HUE + (STRONG_HUE - WEAK_HUE) * strength, 0.8, 1.0);
+            let outline = arrow(center, direction, ARROW_FILL * spacing * strength, SHAFT_WIDTH * pixel, color);
+            if let Ok(arrow) = EntityBuilder::from_shape(ShapeKind::Polygon, outline) {
+                vertices.extend(arrow.build().vertices);
+            }
+        }
+        vertices
+    }
+
+}
+
+impl Default for PhasePortrait {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// outline of an arrow `length` long pointing along `direction`, centered on
+// `center`; short arrows get thinner shafts so their heads stay visible
+fn arrow(center: [f32; 2], direction: [f32; 2], length: f32, shaft_width: f32, color: Color) -> Vec<Vertex> {
+    let [dx, dy] = direction;
+    let (nx, ny) = (-dy, dx);
+    let at = |along: f32, across: f32| Vertex::new(
+        center[0] + dx * (along - length / 2.0) + nx * across,
+        center[1] + dy * (along - length / 2.0) + ny * across,
+        0.0,
+        color
+    );
+    let neck = length * (1.0 - HEAD_LENGTH);
+    let shaft = shaft_width.min(length * HEAD_WIDTH / 2.0) / 2.0;
+    let head = length * HEAD_WIDTH / 2.0;
+    vec![
+        at(0.0, -shaft),
+        at(neck, -shaft),
+        at(neck, -head),
+        at(length, 0.0),
+        at(neck, head),
+        at(neck, shaft),
+        at(0.0, shaft)
+    ]
+}
+
+// the trajectory through `start`, followed backwards and forwards in time
+fn follow(field: &Field, start: Point) -> Vec<Point> {
+    let mut points = integrate(field, start, -1.0);
+    points.reverse();
+    points.pop();
+    points.extend(integrate(field, start, 1.0));
+    points
+}
+
+// RK4 steps from `start`, each sized so the trajectory advances about
+// MAX_STEP_LENGTH; `direction` is -1 to run time backwards
+fn integrate(field: &Field, start: Point, direction: f64) -> Vec<Point> {
+
+    let derivative = |_: f64, y: &State| {
+        let [u, v] = field(y[0], y[1]);
+        Vector::new(vec![direction * u, direction * v])
+    };
+
+    let mut y: State = Vector::new(vec![start[0], start[1]]);
+    let mut points = vec![start];
+    for _ in 0..MAX_STEPS {
+        let [u, v] = field(y[0], y[1]);
+        let speed = (u * u + v * v).sqrt();
+        if speed.is_nan() || speed <= STALL_SPEED {
+            break;
+        }
+        y = rk4(&derivative, 0.0, &y, (MAX_STEP_LENGTH / speed).min(MAX_STEP_TIME));
+        let point = [y[0], y[1]];
+        if !(point[0].is_finite() && point[1].is_finite()) || point[0].hypot(point[1]) > ESCAPE_DISTANCE {
+            break;
+        }
+        points.push(point);
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn rotation_keeps_trajectories_on_circles() {
+        let mut portrait = PhasePortrait::new();
+        portrait.add_trajectory([1.0, 0.0]);
+        // started before there was a field to follow
+        assert!(portrait.trajectory(0).is_empty());
+        portrait.set_field(|x, y| [-y, x]);
+        let points = portrait.trajectory(0);
+        assert_eq!(points.len(), 2 * MAX_STEPS + 1);
+        assert!(points.iter().all(|p| (p[0].hypot(p[1]) - 1.0).abs() < 1e-6));
+    }
+
+    #[test]
+    fn trajectories_end_at_equilibria() {
+        let mut portrait = PhasePortrait::new();
+        // the origin attracts everything, so backwards in time points head out
+        portrait.set_field(|x, y| [-x, -y]);
+        portrait.add_trajectory([2.0, 0.0]);
+        let points = portrait.trajectory(0);
+        assert!(points.len() < 2 * MAX_STEPS + 1);
+        assert!(points.last().unwrap()[0] <= STALL_SPEED);
+        assert!(points.windows(2).all(|pair| pair[1][0] < pair[0][0] && pair[1][1] == 0.0));
+    }
+
+    #[test]
+    fn arrows_scale_with_the_field() {
+        let portrait = PhasePortrait::new();
+        let field: Field = Box::new(|x, _| [x, 0.0]);
+        let vertices = portrait.arrows(&field, [-40.0, -10.0], [40.0, 10.0], 1.0);
+        let reach = |vertices: &[Vertex]| vertices.iter().map(|v| v.position[0]).fold(f32::NEG_INFINITY, f32::max);
+        // arrows at x = -32 and 32, the origin's being too weak to draw
+        assert_eq!(reach(&vertices), 32.0 + ARROW_FILL * ARROW_SPACING / 2.0);
+        assert!(vertices.iter().all(|v| v.color == Color::from_hsv(STRONG_HUE, 0.8, 1.0).in_percentages()));
+    }
+
+}
@@ -0,0 +1,701 @@
+use std::collections::HashMap;
+
+use crate::colors::Color;
+use crate::entities::{Entity, EntityBuilder, ShapeError, ShapeKind, Vertex};
+use crate::pipeline::PipelineKey;
+use crate::simulation::Simulated;
+use crate::transform::Transform;
+
+// side of the square cells bodies are hashed into for the broad phase
+const DEFAULT_CELL_SIZE: f32 = 64.0;
+// solver passes over every contact per step
+const DEFAULT_ITERATIONS: usize = 10;
+const DEFAULT_RESTITUTION: f32 = 0.2;
+const DEFAULT_FRICTION: f32 = 0.5;
+// contacts closing slower than this come to rest instead of bouncing, so
+// stacks settle rather than jitter
+const BOUNCE_THRESHOLD: f32 = 30.0;
+// overlap left alone, and the share of the rest pushed apart every step
+const SLOP: f32 = 0.5;
+const CORRECTION: f32 = 0.4;
+// faces of the second body must separate by this much more to be preferred,
+// so the reference face doesn't flip between steps
+const REFERENCE_BIAS: f32 = 0.05;
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+fn rotate(a: [f32; 2], (sin, cos): (f32, f32)) -> [f32; 2] {
+    [cos * a[0] - sin * a[1], sin * a[0] + cos * a[1]]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Collider {
+    Circle(f32),
+    // convex and counter-clockwise around the body's center of mass
+    Polygon(Vec<[f32; 2]>)
+}
+
+// A circle or convex polygon that moves without deforming. Bodies built with
+// zero density never move and only get collided against.
+pub struct Body {
+    pub position: [f32; 2],
+    pub angle: f32,
+    pub velocity: [f32; 2],
+    pub angular_velocity: f32,
+    collider: Collider,
+    inverse_mass: f32,
+    inverse_inertia: f32,
+    restitution: f32,
+    friction: f32,
+    // triangles around the center of mass, put in place by `transform`
+    mesh: Vec<Vertex>
+}
+
+impl Body {
+
+    // takes the same vertices, in world space, as EntityBuilder::from_shape
+    pub fn from_shape(kind: ShapeKind, vertices: Vec<Vertex>, density: f32) -> Result<Body, ShapeError> {
+
+        // catches miscounted and self-intersecting outlines
+        EntityBuilder::from_shape(kind, vertices.clone())?;
+
+        if let ShapeKind::Circle(radius) = kind {
+            let center = vertices[0];
+            let mesh = EntityBuilder::from_shape(kind, vec![Vertex::new(0.0, 0.0, 0.0, Color::from(center.color))])?.build().vertices;
+            let mass = density * std::f32::consts::PI * radius * radius;
+            return Ok(Self::new(
+                [center.position[0], center.position[1]],
+                Collider::Circle(radius),
+                mass,
+                mass * radius * radius / 2.0,
+                mesh
+            ));
+        }
+
+        let mut outline = vertices;
+        outline.dedup_by(|a, b| a.position == b.position);
+        if outline.len() > 1 && outline[0].position == outline[outline.len() - 1].position {
+            outline.pop();
+        }
+        let point = |i: usize| [outline[i].position[0], outline[i].position[1]];
+        let n = outline.len();
+
+        let area = (0..n).map(|i| cross(point(i), point((i + 1) % n))).sum::<f32>() / 2.0;
+        if area.abs() <= f32::EPSILON {
+            return Err(ShapeError::Degenerate);
+        }
+        if area < 0.0 {
+            outline.reverse();
+        }
+        let point = |i: usize| [outline[i].position[0], outline[i].position[1]];
+        let area = area.abs();
+
+        let turns_right = (0..n).any(|i| {
+            let (a, b, c) = (point(i), point((i + 1) % n), point((i + 2) % n));
+            cross(sub(b, a), sub(c, b)) < -f32::EPSILON * length(sub(b, a)) * length(sub(c, b))
+        });
+        if turns_right {
+            return Err(ShapeError::NotConvex);
+        }
+
+        let centroid = (0..n).fold([0.0, 0.0], |sum, i| {
+            let (a, b) = (point(i), point((i + 1) % n));
+            add(sum, scale(add(a, b), cross(a, b) / (6.0 * area)))
+        });
+        let local: Vec<[f32; 2]> = (0..n).map(|i| sub(point(i), centroid)).collect();
+        // of a uniform lamina, about its centroid
+        let second_moment = (0..n).map(|i| {
+            let (a, b) = (local[i], local[(i + 1) % n]);
+            cross(a, b) * (dot(a, a) + dot(a, b) + dot(b, b))
+        }).sum::<f32>() / 12.0;
+
+        let mesh = EntityBuilder::from_shape(
+            ShapeKind::Polygon,
+            outline.iter().zip(&local).map(|(vertex, &[x, y])| Vertex::new(x, y, 0.0, vertex.color.into())).collect()
+        )?.build().vertices;
+
+        Ok(Self::new(centroid, Collider::Polygon(local), density * area, density * second_moment, mesh))
+    }
+
+    fn new(position: [f32; 2], collider: Collider, mass: f32, inertia: f32, mesh: Vec<Vertex>) -> Body {
+        let invert = |value: f32| if value > 0.0 { 1.0 / value } else { 0.0 };
+        Self {
+            position,
+            angle: 0.0,
+            velocity: [0.0, 0.0],
+            angular_velocity: 0.0,
+            collider,
+            inverse_mass: invert(mass),
+            inverse_inertia: invert(inertia),
+            restitution: DEFAULT_RESTITUTION,
+            friction: DEFAULT_FRICTION,
+            mesh
+        }
+    }
+
+    pub fn moving(mut self, velocity: [f32; 2], angular_velocity: f32) -> Body {
+        self.velocity = velocity;
+        self.angular_velocity = angular_velocity;
+        self
+    }
+
+    // turns the body about its center of mass
+    pub fn rotated(mut self, angle: f32) -> Body {
+        self.angle = angle;
+        self
+    }
+
+    // 0 for no bounce at all, 1 for perfectly elastic
+    pub fn restitution(mut self, restitution: f32) -> Body {
+        self.restitution = restitution;
+        self
+    }
+
+    pub fn friction(mut self, friction: f32) -> Body {
+        self.friction = friction;
+        self
+    }
+
+    pub fn collider(&self) -> &Collider {
+        &self.collider
+    }
+
+    pub fn is_static(&self) -> bool {
+        self.inverse_mass == 0.0
+    }
+
+    // from the body's own space, centered on its center of mass, to the world
+    pub fn transform(&self) -> Transform {
+        Transform::rotation(self.angle).then(&Transform::translation(self.position[0], self.position[1]))
+    }
+
+    pub fn entity(&self) -> Entity {
+        let mut entity = Entity::new(self.mesh.clone(), PipelineKey::default());
+        entity.transform = self.transform();
+        entity
+    }
+
+    fn placed(&self) -> Placed {
+        match &self.collider {
+            Collider::Circle(radius) => Placed::Circle { center: self.position, radius: *radius },
+            Collider::Polygon(local) => {
+                let turn = self.angle.sin_cos();
+                let points: Vec<[f32; 2]> = local.iter().map(|&p| add(rotate(p, turn), self.position)).collect();
+                let normals = (0..points.len()).map(|i| {
+                    let edge = sub(points[(i + 1) % points.len()], points[i]);
+                    scale([edge[1], -edge[0]], 1.0 / length(edge))
+                }).collect();
+                Placed::Polygon { points, normals }
+            }
+        }
+    }
+
+    // of the point `offset` away from the center of mass
+    fn velocity_at(&self, offset: [f32; 2]) -> [f32; 2] {
+        add(self.velocity, scale([-offset[1], offset[0]], self.angular_velocity))
+    }
+
+    fn apply_impulse(&mut self, impulse: [f32; 2], offset: [f32; 2]) {
+        self.velocity = add(self.velocity, scale(impulse, self.inverse_mass));
+        self.angular_velocity += self.inverse_inertia * cross(offset, impulse);
+    }
+
+}
+
+// a collider moved into world space for one step
+enum Placed {
+    Circle { center: [f32; 2], radius: f32 },
+    // outward normals[i] belongs to the edge from points[i] to points[i + 1]
+    Polygon { points: Vec<[f32; 2]>, normals: Vec<[f32; 2]> }
+}
+
+impl Placed {
+
+    fn bounds(&self) -> ([f32; 2], [f32; 2]) {
+        match self {
+            Self::Circle { center, radius } => (sub(*center, [*radius; 2]), add(*center, [*radius; 2])),
+            Self::Polygon { points, .. } => points.iter().fold(
+                ([f32::INFINITY; 2], [f32::NEG_INFINITY; 2]),
+                |(min, max), p| ([min[0].min(p[0]), min[1].min(p[1])], [max[0].max(p[0]), max[1].max(p[1])])
+            )
+        }
+    }
+
+}
+
+// where two bodies touch; `normal` points from the first towards the second
+#[derive(Clone, Debug, PartialEq)]
+struct Manifold {
+    normal: [f32; 2],
+    // each with how deep the bodies overlap there
+    points: Vec<([f32; 2], f32)>
+}
+
+impl Manifold {
+
+    fn flipped(mut self) -> Manifold {
+        self.normal = scale(self.normal, -1.0);
+        self
+    }
+
+}
+
+fn collide(a: &Placed, b: &Placed) -> Option<Manifold> {
+    match (a, b) {
+        (Placed::Circle { center: ca, radius: ra }, Placed::Circle { center: cb, radius: rb }) => {
+            let between = sub(*cb, *ca);
+            let distance = length(between);
+            if distance >= ra + rb {
+                return None;
+            }
+            let normal = if distance > 0.0 { scale(between, 1.0 / distance) } else { [0.0, 1.0] };
+            let depth = ra + rb - distance;
+            Some(Manifold { normal, points: vec![(add(*ca, scale(normal, ra - depth / 2.0)), depth)] })
+        },
+        (Placed::Polygon { points, normals }, Placed::Circle { center, radius }) => polygon_circle(points, normals, *center, *radius),
+        (Placed::Circle { center, radius }, Placed::Polygon { points, normals }) => {
+            polygon_circle(points, normals, *center, *radius).map(Manifold::flipped)
+        },
+        (Placed::Polygon { points: pa, normals: na }, Placed::Polygon { points: pb, normals: nb }) => polygons((pa, na), (pb, nb))
+    }
+}
+
+fn polygon_circle(points: &[[f32; 2]], normals: &[[f32; 2]], center: [f32; 2], radius: f32) -> Option<Manifold> {
+
+    let (face, separation) = (0..points.len())
+        .map(|i| (i, dot(normals[i], sub(center, points[i]))))
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if separation >= radius {
+        return None;
+    }
+
+    let (normal, depth) = if separation <= 0.0 {
+        // the center is inside, so push out through the nearest face
+        (normals[face], radius - separation)
+    } else {
+        // closest point of the nearest face, which may be one of its corners
+        let start = points[face];
+        let edge = sub(points[(face + 1) % points.len()], start);
+        let along = (dot(sub(center, start), edge) / dot(edge, edge)).clamp(0.0, 1.0);
+        let away = sub(center, add(start, scale(edge, along)));
+        let distance = length(away);
+        if distance >= radius {
+            return None;
+        }
+        (scale(away, 1.0 / distance), radius - distance)
+    };
+
+    Some(Manifold { normal, points: vec![(sub(center, scale(normal, radius - depth / 2.0)), depth)] })
+}
+
+// the face of `a` that `b` lies furthest outside of, and by how much
+fn max_separation(a: (&[[f32; 2]], &[[f32; 2]]), b: &[[f32; 2]]) -> (usize, f32) {
+    let (points, normals) = a;
+    (0..points.len())
+        .map(|i| (i, b.iter().map(|&p| dot(normals[i], sub(p, points[i]))).fold(f32::INFINITY, f32::min)))
+        .max_by(|x, y| x.1.total_cmp(&y.1))
+        .unwrap()
+}
+
+// keeps the part of the segment where dot(normal, p) <= offset
+fn clip(segment: [[f32; 2]; 2], normal: [f32; 2], offset: f32) -> Option<[[f32; 2]; 2]> {
+    let [p, q] = segment;
+    let (dp, dq) = (dot(normal, p) - offset, dot(normal, q) - offset);
+    let mut kept = vec![];
+    if dp <= 0.0 {
+        kept.push(p);
+    }
+    if dq <= 0.0 {
+        kept.push(q);
+    }
+    if dp * dq < 0.0 {
+        kept.push(add(p, scale(sub(q, p), dp / (dp - dq))));
+    }
+    (kept.len() == 2).then(|| [kept[0], kept[1]])
+}
+
+// Separating axis test over the faces of both polygons. When nothing separates
+// them, the edge of the other polygon most opposed to the least penetrated face
+// is clipped to that face to find up to two contact points.
+fn polygons(a: (&[[f32; 2]], &[[f32; 2]]), b: (&[[f32; 2]], &[[f32; 2]])) -> Option<Manifold> {
+
+    let (face_a, separation_a) = max_separation(a, b.0);
+    if separation_a > 0.0 {
+        return None;
+    }
+    let (face_b, separation_b) = max_separation(b, a.0);
+    if separation_b > 0.0 {
+        return None;
+    }
+
+    let flip = separation_b > separation_a + REFERENCE_BIAS;
+    let ((reference, reference_normals), (incident, incident_normals), face) = if flip {
+        (b, a, face_b)
+    } else {
+        (a, b, face_a)
+    };
+
+    let normal = reference_normals[face];
+    let incident_face = (0..incident.len())
+        .min_by(|&i, &j| dot(normal, incident_normals[i]).total_cmp(&dot(normal, incident_normals[j])))
+        .unwrap();
+    let edge = [incident[incident_face], incident[(incident_face + 1) % incident.len()]];
+
+    let start = reference[face];
+    let end = reference[(face + 1) % reference.len()];
+    let tangent = scale(sub(end, start), 1.0 / length(sub(end, start)));
+    let edge = clip(edge, scale(tangent, -1.0), -dot(tangent, start))?;
+    let edge = clip(edge, tangent, dot(tangent, end))?;
+
+    let surface = dot(normal, start);
+    let points: Vec<([f32; 2], f32)> = edge.iter()
+                                           .map(|&p| (p, surface - dot(normal, p)))
+                                           .filter(|&(_, depth)| depth >= 0.0)
+                                           .collect();
+    if points.is_empty() {
+        return None;
+    }
+
+    let manifold = Manifold { normal, points };
+    Some(if flip { manifold.flipped() } else { manifold })
+}
+
+// one contact point between bodies a and b, as the solver sees it
+struct Constraint {
+    a: usize,
+    b: usize,
+    normal: [f32; 2],
+    // from each center of mass to the contact point
+    offset_a: [f32; 2],
+    offset_b: [f32; 2],
+    normal_mass: f32,
+    tangent_mass: f32,
+    friction: f32,
+    // separating speed restitution asks for
+    bounce: f32,
+    // accumulated over the iterations, then clamped as a whole
+    normal_impulse: f32,
+    tangent_impulse: f32
+}
+
+fn pair_mut(bodies: &mut [Body], a: usize, b: usize) -> (&mut Body, &mut Body) {
+    let (first, second) = bodies.split_at_mut(b);
+    (&mut first[a], &mut second[0])
+}
+
+// Rigid bodies under constant gravity. Each step hashes bodies into a grid to
+// find the pairs worth testing, collides those exactly, and resolves contacts
+// with sequential impulses before moving everything.
+pub struct World {
+    bodies: Vec<Body>,
+    gravity: [f32; 2],
+    cell_size: f32,
+    iterations: usize
+}
+
+impl World {
+
+    pub fn new(gravity: [f32; 2]) -> World {
+        Self { bodies: vec![], gravity, cell_size: DEFAULT_CELL_SIZE, iterations: DEFAULT_ITERATIONS }
+    }
+
+    // returns the body's index
+    pub fn add(&mut self, body: Body) -> usize {
+        self.bodies.push(body);
+        self.bodies.len() - 1
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    pub fn body_mut(&mut self, index: usize) -> &mut Body {
+        &mut self.bodies[index]
+    }
+
+    pub fn set_gravity(&mut self, gravity: [f32; 2]) {
+        self.gravity = gravity;
+    }
+
+    // best around the size of a typical body
+    pub fn set_cell_size(&mut self, cell_size: f32) {
+        self.cell_size = cell_size;
+    }
+
+    pub fn set_iterations(&mut self, iterations: usize) {
+        self.iterations = iterations;
+    }
+
+    fn placed(&self) -> Vec<Placed> {
+        self.bodies.iter().map(Body::placed).collect()
+    }
+
+    // pairs of bodies, lower index first, whose bounds overlap; two static
+    // bodies are never paired
+    fn broad_phase(&self, placed: &[Placed]) -> Vec<(usize, usize)> {
+
+        let bounds: Vec<([f32; 2], [f32; 2])> = placed.iter().map(Placed::bounds).collect();
+        let cell = |value: f32| (value / self.cell_size).floor() as i32;
+
+        let mut grid: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (i, (min, max)) in bounds.iter().enumerate() {
+            for x in cell(min[0])..=cell(max[0]) {
+                for y in cell(min[1])..=cell(max[1]) {
+                    grid.entry((x, y)).or_default().push(i);
+                }
+            }
+        }
+
+        let overlapping = |a: usize, b: usize| {
+            let ((min_a, max_a), (min_b, max_b)) = (bounds[a], bounds[b]);
+            min_a[0] <= max_b[0] && min_b[0] <= max_a[0] && min_a[1] <= max_b[1] && min_b[1] <= max_a[1]
+        };
+        let mut pairs = vec![];
+        for occupants in grid.values() {
+            for (k, &a) in occupants.iter().enumerate() {
+                for &b in &occupants[k + 1..] {
+                    if !(self.bodies[a].is_static() && self.bodies[b].is_static()) && overlapping(a, b) {
+                        pairs.push((a, b));
+                    }
+                }
+            }
+        }
+        // bodies sharing several cells were paired in each of them
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn constraints(&self, a: usize, b: usize, manifold: &Manifold) -> Vec<Constraint> {
+        let (body_a, body_b) = (&self.bodies[a], &self.bodies[b]);
+        let normal = manifold.normal;
+        let tangent = [normal[1], -normal[0]];
+        let friction = (body_a.friction * body_b.friction).sqrt();
+        let restitution = body_a.restitution.max(body_b.restitution);
+        let effective_mass = |offset_a: [f32; 2], offset_b: [f32; 2], direction: [f32; 2]| {
+            let (turn_a, turn_b) = (cross(offset_a, direction), cross(offset_b, direction));
+            let k = body_a.inverse_mass + body_b.inverse_mass
+                  + body_a.inverse_inertia * turn_a * turn_a
+                  + body_b.inverse_inertia * turn_b * turn_b;
+            if k > 0.0 { 1.0 / k } else { 0.0 }
+        };
+        manifold.points.iter().map(|&(point, _)| {
+            let offset_a = sub(point, body_a.position);
+            let offset_b = sub(point, body_b.position);
+            let closing = dot(sub(body_b.velocity_at(offset_b), body_a.velocity_at(offset_a)), normal);
+            Constraint {
+                a,
+                b,
+                normal,
+                offset_a,
+                offset_b,
+                normal_mass: effective_mass(offset_a, offset_b, normal),
+                tangent_mass: effective_mass(offset_a, offset_b, tangent),
+                friction,
+                bounce: if closing < -BOUNCE_THRESHOLD { -restitution * closing } else { 0.0 },
+                normal_impulse: 0.0,
+                tangent_impulse: 0.0
+            }
+        }).collect()
+    }
+
+    fn solve(&mut self, constraint: &mut Constraint) {
+        let (a, b) = pair_mut(&mut self.bodies, constraint.a, constraint.b);
+        let (offset_a, offset_b) = (constraint.offset_a, constraint.offset_b);
+        let normal = constraint.normal;
+        let tangent = [normal[1], -normal[0]];
+
+        let apply = |a: &mut Body, b: &mut Body, impulse: [f32; 2]| {
+            a.apply_impulse(scale(impulse, -1.0), offset_a);
+            b.apply_impulse(impulse, offset_b);
+        };
+
+        let relative = sub(b.velocity_at(offset_b), a.velocity_at(offset_a));
+        let lambda = constraint.normal_mass * (constraint.bounce - dot(relative, normal));
+        let total = (constraint.normal_impulse + lambda).max(0.0);
+        apply(a, b, scale(normal, total - constraint.normal_impulse));
+        constraint.normal_impulse = total;
+
+        // Coulomb friction, bounded by how hard the bodies are pressed together
+        let relative = sub(b.velocity_at(offset_b), a.velocity_at(offset_a));
+        let lambda = -constraint.tangent_mass * dot(relative, tangent);
+        let limit = constraint.friction * constraint.normal_impulse;
+        let total = (constraint.tangent_impulse + lambda).clamp(-limit, limit);
+        apply(a, b, scale(tangent, total - constraint.tangent_impulse));
+        constraint.tangent_impulse = total;
+    }
+
+    pub fn step(&mut self, dt: f32) {
+
+        for body in self.bodies.iter_mut().filter(|body| !body.is_static()) {
+            body.velocity = add(body.velocity, scale(self.gravity, dt));
+        }
+
+        let placed = self.placed();
+        let contacts: Vec<(usize, usize, Manifold)> = self.broad_phase(&placed)
+            .into_iter()
+            .filter_map(|(a, b)| collide(&placed[a], &placed[b]).map(|manifold| (a, b, manifold)))
+            .collect();
+
+        let mut constraints: Vec<Constraint> = contacts.iter()
+                                                       .flat_map(|(a, b, manifold)| self.constraints(*a, *b, manifold))
+                                                       .collect();
+        for _ in 0..self.iterations {
+            for constraint in &mut constraints {
+                self.solve(constraint);
+            }
+        }
+
+        for body in &mut self.bodies {
+            body.position = add(body.position, scale(body.velocity, dt));
+            body.angle += body.angular_velocity * dt;
+        }
+
+        // velocities alone let bodies sink into each other over time
+        for (a, b, manifold) in contacts {
+            let (a, b) = pair_mut(&mut self.bodies, a, b);
+            let total_inverse_mass = a.inverse_mass + b.inverse_mass;
+            let depth = manifold.points.iter().map(|&(_, depth)| depth).fold(0.0, f32::max);
+            let push = scale(manifold.normal, (depth - SLOP).max(0.0) * CORRECTION / total_inverse_mass);
+            a.position = sub(a.position, scale(push, a.inverse_mass));
+            b.position = add(b.position, scale(push, b.inverse_mass));
+        }
+    }
+
+}
+
+impl Simulated for World {
+
+    fn step(&mut self, _t: f64, dt: f64) {
+        World::step(self, dt as f32);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        self.bodies.iter().map(Body::entity).collect()
+    }
+
+    // bodies never change shape, so only their transforms need updating
+    fn redraw(&self, entities: &mut Vec<Entity>) {
+        if entities.len() != self.bodies.len() {
+            *entities = self.entities();
+            return;
+        }
+        for (entity, body) in entities.iter_mut().zip(&self.bodies) {
+            entity.transform = body.transform();
+        }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::colors::WHITE;
+
+    fn square(center: [f32; 2], half: f32, density: f32) -> Body {
+        let [x, y] = center;
+        Body::from_shape(ShapeKind::Rectangle, vec![
+            Vertex::new(x - half, y + half, 0.0, WHITE),
+            Vertex::new(x - half, y - half, 0.0, WHITE),
+            Vertex::new(x + half, y - half, 0.0, WHITE),
+            Vertex::new(x + half, y + half, 0.0, WHITE)
+        ], density).unwrap()
+    }
+
+    fn ball(center: [f32; 2], radius: f32) -> Body {
+        Body::from_shape(ShapeKind::Circle(radius), vec![Vertex::new(center[0], center[1], 0.0, WHITE)], 1.0).unwrap()
+    }
+
+    #[test]
+    fn boxes_collide_along_their_shallowest_overlap() {
+        let manifold = collide(&square([0.0, 0.0], 10.0, 1.0).placed(), &square([15.0, 5.0], 10.0, 1.0).placed()).unwrap();
+        assert_eq!(manifold.normal, [1.0, 0.0]);
+        assert_eq!(manifold.points.len(), 2);
+        assert!(manifold.points.iter().all(|&(_, depth)| depth == 5.0));
+        assert_eq!(collide(&square([0.0, 0.0], 10.0, 1.0).placed(), &square([25.0, 0.0], 10.0, 1.0).placed()), None);
+    }
+
+    #[test]
+    fn rejects_concave_outlines() {
+        let chevron = vec![
+            Vertex::new(0.0, 0.0, 0.0, WHITE),
+            Vertex::new(20.0, 10.0, 0.0, WHITE),
+            Vertex::new(0.0, 20.0, 0.0, WHITE),
+            Vertex::new(5.0, 10.0, 0.0, WHITE)
+        ];
+        assert!(matches!(Body::from_shape(ShapeKind::Polygon, chevron, 1.0), Err(ShapeError::NotConvex)));
+    }
+
+    #[test]
+    fn equal_elastic_balls_swap_velocities() {
+        let mut world = World::new([0.0, 0.0]);
+        world.add(ball([-9.9, 0.0], 10.0).moving([100.0, 0.0], 0.0).restitution(1.0));
+        world.add(ball([9.9, 0.0], 10.0).restitution(1.0));
+        world.step(1.0 / 120.0);
+        assert!(world.bodies()[0].velocity[0].abs() < 1e-3);
+        assert!((world.bodies()[1].velocity[0] - 100.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn redrawing_moves_the_meshes_already_drawn() {
+        let mut world = World::new([0.0, 0.0]);
+        world.add(ball([0.0, 0.0], 10.0).moving([100.0, 0.0], 0.0));
+        let mut entities = world.entities();
+        let mesh = entities[0].vertices.as_ptr();
+        world.step(0.5);
+        world.redraw(&mut entities);
+        assert_eq!(entities[0].vertices.as_ptr(), mesh);
+        assert_eq!(entities[0].transform, Transform::translation(50.0, 0.0));
+    }
+
+    #[test]
+    fn boxes_come_to_rest_on_the_ground() {
+        let mut world = World::new([0.0, -500.0]);
+        world.add(square([0.0, -100.0], 100.0, 0.0));
+        world.add(square([0.0, 40.0], 10.0, 1.0).rotated(0.3));
+        for _ in 0..480 {
+            world.step(1.0 / 120.0);
+        }
+        let settled = &world.bodies()[1];
+        assert!(length(settled.velocity) < 1.0 && settled.angular_velocity.abs() < 0.1);
+        // flat on its face, a slop's depth into the ground at most
+        assert!((settled.position[1] - 10.0).abs() < SLOP);
+        assert!(settled.angle.sin().abs() < 1e-2 || settled.angle.cos().abs() < 1e-2);
+    }
+
+    #[test]
+    fn broad_phase_pairs_only_neighbours() {
+        let mut world = World::new([0.0, 0.0]);
+        world.add(ball([0.0, 0.0], 10.0));
+        world.add(ball([300.0, 0.0], 10.0));
+        world.add(ball([15.0, 5.0], 10.0));
+        world.add(square([300.0, 0.0], 200.0, 0.0));
+        world.add(square([0.0, 0.0], 200.0, 0.0));
+        assert_eq!(world.broad_phase(&world.placed()), vec![(0, 2), (0, 4), (1, 3), (2, 4)]);
+    }
+
+}
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+
+use crate::entities::{Instance, Vertex};
+
+// how many vec4s of parameters follow the view projection in every shader's globals
+pub const MAX_PARAMETERS: usize = 64;
+
+// the CPU counterpart of a custom fragment shader, for renderers without a GPU:
+// maps the interpolated vertex position and color, plus the frame's parameters,
+// to the color of one pixel
+pub type Fragment = fn([f32; 2], [f32; 3], &[[f32; 4]]) -> [f32; 3];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ShaderId(pub(crate) usize);
+
+impl ShaderId {
+
+    // shader.wgsl, instanced.wgsl and textured.wgsl, registered by every
+    // renderer on creation
+    pub const DEFAULT: ShaderId = ShaderId(0);
+    pub const INSTANCED: ShaderId = ShaderId(1);
+    pub const TEXTURED: ShaderId = ShaderId(2);
+
+}
+
+// an image uploaded to a renderer, see `Renderer::register_texture`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureId(pub(crate) usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Blend {
+    Replace,
+    Alpha
+}
+
+impl Blend {
+
+    fn state(&self) -> wgpu::BlendState {
+        match self {
+            Self::Replace => wgpu::BlendState::REPLACE,
+            Self::Alpha => wgpu::BlendState::ALPHA_BLENDING
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topology {
+    TriangleList,
+    TriangleStrip
+}
+
+impl Topology {
+
+    fn primitive(&self) -> wgpu::PrimitiveTopology {
+        match self {
+            Self::TriangleList => wgpu::PrimitiveTopology::TriangleList,
+            Self::TriangleStrip => wgpu::PrimitiveTopology::TriangleStrip
+        }
+    }
+
+    // whether the vertices of two neighbouring entities can be drawn as one run
+    pub fn mergeable(&self) -> bool {
+        match self {
+            Self::TriangleList => true,
+            Self::TriangleStrip => false
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PipelineKey {
+    pub shader: ShaderId,
+    pub blend: Blend,
+    pub topology: Topology,
+    // whether a second buffer of `Instance`s follows the vertices
+    pub instanced: bool,
+    // whether a texture and its sampler are bound to group 1
+    pub textured: bool
+}
+
+impl Default for PipelineKey {
+    fn default() -> Self {
+        Self {
+            shader: ShaderId::DEFAULT,
+            blend: Blend::Replace,
+            topology: Topology::TriangleList,
+            instanced: false,
+            textured: false
+        }
+    }
+}
+
+// Compiles every shader once and every pipeline the first time an entity asks
+// for it; all pipelines share the globals and the `Vertex` buffer layout,
+// instanced ones followed by the `Instance` buffer layout and textured ones
+// binding their texture after the globals.
+pub struct PipelineCache {
+    format: wgpu::TextureFormat,
+    globals_layout: wgpu::BindGroupLayout,
+    texture_layout: wgpu::BindGroupLayout,
+    layout: wgpu::PipelineLayout,
+    textured_layout: wgpu::PipelineLayout,
+    shaders: Vec<wgpu::ShaderModule>,
+    pipelines: HashMap<PipelineKey, wgpu::RenderPipeline>
+}
+
+impl PipelineCache {
+
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> PipelineCache {
+
+        // group 0 => per-frame uniforms, the view projection followed by
+        // MAX_PARAMETERS parameters for custom shaders
+        let globals_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Globals Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None
+                },
+                count: None
+            }]
+        });
+
+        // group 1 => the texture of a textured entity and how it is filtered
+        let texture_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false
+                    },
+                    count: None
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None
+                }
+            ]
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Render Pipeline Layout"),
+            bind_group_layouts: &[&globals_layout],
+            push_constant_ranges: &[]
+        });
+
+        let textured_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Textured Render Pipeline Layout"),
+            bind_group_layouts: &[&globals_layout, &texture_layout],
+            push_constant_ranges: &[]
+        });
+
+        let mut cache = Self {
+            format,
+            globals_layout,
+            texture_layout,
+            layout,
+            textured_layout,
+            shaders: vec![],
+            pipelines: HashMap::new()
+        };
+
+        cache.register_shader(device, "Shader", include_str!("shader.wgsl"));
+        cache.register_shader(device, "Instanced Shader", include_str!("instanced.wgsl"));
+        cache.register_shader(device, "Textured Shader", include_str!("textured.wgsl"));
+
+        cache
+    }
+
+    pub fn globals_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.globals_layout
+    }
+
+    pub fn texture_layout(&self) -> &wgpu::BindGroupLayout {
+        &self.texture_layout
+    }
+
+    // custom shaders must expose `vertex_shader_main` and `fragment_shader_main`
+    // and consume the same vertex layout and globals binding as shader.wgsl,
+    // optionally declaring the parameters after the view projection
+    pub fn register_shader(&mut self, device: &wgpu::Device, label: &str, source: &str) -> ShaderId {
+        self.shaders.push(device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some(label),
+            source: wgpu::ShaderSource::Wgsl(source.into())
+        }));
+        ShaderId(self.shaders.len() - 1)
+    }
+
+    pub fn prepare(&mut self, device: &wgpu::Device, key: PipelineKey) {
+        if !self.pipelines.contains_key(&key) {
+            let pipeline = self.create_pipeline(device, key);
+            self.pipelines.insert(key, pipeline);
+        }
+    }
+
+    pub fn get(&self, key: &PipelineKey) -> &wgpu::RenderPipeline {
+        self.pipelines.get(key).expect("pipeline was never prepared")
+    }
+
+    fn create_pipeline(&self, device: &wgpu::Device, key: PipelineKey) -> wgpu::RenderPipeline {
+
+        let shader = self.shaders.get(key.shader.0).expect("shader was never registered");
+        let buffers = if key.instanced {
+            vec![Vertex::desc(), Instance::desc()]
+        } else {
+            vec![Vertex::desc()]
+        };
+
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(if key.textured { &self.textured_layout } else { &self.layout }),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vertex_shader_main",
+                buffers: &buffers
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fragment_shader_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: self.format,
+                    blend: Some(key.blend.state()),
+                    write_mask: wgpu::ColorWrites::ALL
+                })]
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: key.topology.primitive(),
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false
+            },
+            multiview: None
+        })
+    }
+
+}
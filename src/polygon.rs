@@ -0,0 +1,204 @@
+use crate::entities::{ShapeError, Vertex};
+
+// Triangulates a simple polygon outline by ear clipping. The outline may be
+// concave and wound either way; the triangles come out counter-clockwise so
+// they survive back-face culling, and keep the colors of the outline vertices.
+pub fn triangulate(outline: &[Vertex]) -> Result<Vec<Vertex>, ShapeError> {
+
+    if outline.len() < 3 {
+        return Err(ShapeError::TooFewPoints(outline.len()));
+    }
+
+    let mut points = distinct_points(outline);
+    if points.len() < 3 || collinear(&points) {
+        return Err(ShapeError::Degenerate);
+    }
+    if self_intersects(&points) {
+        return Err(ShapeError::SelfIntersecting);
+    }
+    if signed_area(&points).abs() <= f32::EPSILON {
+        return Err(ShapeError::Degenerate);
+    }
+    if signed_area(&points) < 0.0 {
+        points.reverse();
+    }
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::with_capacity(3 * (points.len() - 2));
+
+    while remaining.len() > 3 {
+        let ear = (0..remaining.len()).find_map(|i| {
+            let prev = remaining[(i + remaining.len() - 1) % remaining.len()];
+            let next = remaining[(i + 1) % remaining.len()];
+            let turn = cross(&points[prev], &points[remaining[i]], &points[next]);
+            if turn == 0.0 {
+                // a straight-through vertex adds nothing to the shape
+                return Some((i, None));
+            }
+            if turn > 0.0 && !remaining.iter().any(|&other| {
+                other != prev && other != remaining[i] && other != next
+                    && inside_triangle(&points[other], &points[prev], &points[remaining[i]], &points[next])
+            }) {
+                return Some((i, Some([prev, remaining[i], next])));
+            }
+            None
+        });
+
+        // a simple polygon always has an ear, so only rounding can get us here
+        let (i, triangle) = ear.ok_or(ShapeError::Degenerate)?;
+        if let Some(triangle) = triangle {
+            triangles.extend(triangle.iter().map(|&index| points[index]));
+        }
+        remaining.remove(i);
+    }
+
+    if cross(&points[remaining[0]], &points[remaining[1]], &points[remaining[2]]) > 0.0 {
+        triangles.extend(remaining.iter().map(|&index| points[index]));
+    }
+
+    Ok(triangles)
+}
+
+// drops repeated neighbours, including a closing point equal to the first
+fn distinct_points(outline: &[Vertex]) -> Vec<Vertex> {
+    let mut points: Vec<Vertex> = Vec::with_capacity(outline.len());
+    for vertex in outline {
+        if !points.last().is_some_and(|last| same_position(last, vertex)) {
+            points.push(*vertex);
+        }
+    }
+    while points.len() > 1 && same_position(&points[0], points.last().unwrap()) {
+        points.pop();
+    }
+    points
+}
+
+fn same_position(a: &Vertex, b: &Vertex) -> bool {
+    a.position[0] == b.position[0] && a.position[1] == b.position[1]
+}
+
+pub fn collinear(points: &[Vertex]) -> bool {
+    points.windows(2).all(|pair| cross(&points[0], &pair[0], &pair[1]) == 0.0)
+}
+
+// shoelace formula, positive for counter-clockwise outlines
+fn signed_area(points: &[Vertex]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in points.iter().enumerate() {
+        let b = &points[(i + 1) % points.len()];
+        area += a.position[0] * b.position[1] - b.position[0] * a.position[1];
+    }
+    area / 2.0
+}
+
+// positive when a => b => c turns left
+fn cross(a: &Vertex, b: &Vertex, c: &Vertex) -> f32 {
+    (b.position[0] - a.position[0]) * (c.position[1] - a.position[1])
+        - (b.position[1] - a.position[1]) * (c.position[0] - a.position[0])
+}
+
+// inclusive of the edges, so a vertex touching a candidate ear blocks it
+fn inside_triangle(p: &Vertex, a: &Vertex, b: &Vertex, c: &Vertex) -> bool {
+    cross(a, b, p) >= 0.0 && cross(b, c, p) >= 0.0 && cross(c, a, p) >= 0.0
+}
+
+// whether `p` lies on the triangle a => b => c, edges included, whichever way
+// it is wound. A triangle without area contains nothing, rather than the
+// whole line through it
+pub fn triangle_contains(p: &Vertex, a: &Vertex, b: &Vertex, c: &Vertex) -> bool {
+    !collinear(&[*a, *b, *c]) && (inside_triangle(p, a, b, c) || inside_triangle(p, a, c, b))
+}
+
+fn on_segment(p: &Vertex, a: &Vertex, b: &Vertex) -> bool {
+    p.position[0] >= a.position[0].min(b.position[0]) && p.position[0] <= a.position[0].max(b.position[0])
+        && p.position[1] >= a.position[1].min(b.position[1]) && p.position[1] <= a.position[1].max(b.position[1])
+}
+
+fn segments_intersect(a: &Vertex, b: &Vertex, c: &Vertex, d: &Vertex) -> bool {
+    let (abc, abd) = (cross(a, b, c), cross(a, b, d));
+    let (cda, cdb) = (cross(c, d, a), cross(c, d, b));
+    if ((abc > 0.0 && abd < 0.0) || (abc < 0.0 && abd > 0.0))
+        && ((cda > 0.0 && cdb < 0.0) || (cda < 0.0 && cdb > 0.0)) {
+        return true;
+    }
+    (abc == 0.0 && on_segment(c, a, b))
+        || (abd == 0.0 && on_segment(d, a, b))
+        || (cda == 0.0 && on_segment(a, c, d))
+        || (cdb == 0.0 && on_segment(b, c, d))
+}
+
+fn self_intersects(points: &[Vertex]) -> bool {
+    let n = points.len();
+    for i in 0..n {
+        let (a, b) = (&points[i], &points[(i + 1) % n]);
+
+        // neighbouring edges share a vertex, so they only clash when the
+        // outline doubles back on itself
+        let c = &points[(i + 2) % n];
+        if cross(a, b, c) == 0.0 && (on_segment(c, a, b) || on_segment(a, b, c)) {
+            return true;
+        }
+
+        for j in (i + 2)..n {
+            if i == 0 && j == n - 1 {
+                continue;
+            }
+            if segments_intersect(a, b, &points[j], &points[(j + 1) % n]) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::colors::WHITE;
+
+    fn outline(points: &[[f32; 2]]) -> Vec<Vertex> {
+        points.iter().map(|p| Vertex::new(p[0], p[1], 0.0, WHITE)).collect()
+    }
+
+    fn area(triangles: &[Vertex]) -> f32 {
+        triangles.chunks_exact(3).map(|t| cross(&t[0], &t[1], &t[2]) / 2.0).sum()
+    }
+
+    #[test]
+    fn concave_outline_covers_its_area() {
+        // an L shape wound clockwise
+        let l = outline(&[[0.0, 0.0], [0.0, 20.0], [10.0, 20.0], [10.0, 10.0], [20.0, 10.0], [20.0, 0.0]]);
+        let triangles = triangulate(&l).unwrap();
+        assert_eq!(triangles.len(), 3 * 4);
+        assert!(triangles.chunks_exact(3).all(|t| cross(&t[0], &t[1], &t[2]) > 0.0));
+        assert_eq!(area(&triangles), 300.0);
+    }
+
+    #[test]
+    fn collinear_and_repeated_points_are_skipped() {
+        let square = outline(&[[0.0, 0.0], [5.0, 0.0], [10.0, 0.0], [10.0, 10.0], [10.0, 10.0], [0.0, 10.0], [0.0, 0.0]]);
+        let triangles = triangulate(&square).unwrap();
+        assert_eq!(area(&triangles), 100.0);
+    }
+
+    #[test]
+    fn triangles_contain_points_either_way_round() {
+        let corners = outline(&[[0.0, 0.0], [10.0, 0.0], [0.0, 10.0]]);
+        let [a, b, c] = [corners[0], corners[1], corners[2]];
+        let [inside, edge, outside] = outline(&[[2.0, 2.0], [5.0, 5.0], [6.0, 6.0]]).try_into().unwrap();
+        assert!(triangle_contains(&inside, &a, &b, &c) && triangle_contains(&inside, &a, &c, &b));
+        assert!(triangle_contains(&edge, &a, &b, &c));
+        assert!(!triangle_contains(&outside, &a, &b, &c) && !triangle_contains(&outside, &c, &b, &a));
+    }
+
+    #[test]
+    fn invalid_outlines_are_rejected() {
+        assert!(matches!(triangulate(&outline(&[[0.0, 0.0], [1.0, 1.0]])), Err(ShapeError::TooFewPoints(2))));
+        assert!(matches!(triangulate(&outline(&[[0.0, 0.0], [1.0, 1.0], [2.0, 2.0]])), Err(ShapeError::Degenerate)));
+        let bowtie = outline(&[[0.0, 0.0], [10.0, 10.0], [10.0, 0.0], [0.0, 10.0]]);
+        assert!(matches!(triangulate(&bowtie), Err(ShapeError::SelfIntersecting)));
+    }
+
+}
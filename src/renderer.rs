@@ -0,0 +1,78 @@
+use crate::colors::Background;
+use crate::entities::Entity;
+use crate::image::Image;
+use crate::pipeline::{Fragment, ShaderId, TextureId};
+use crate::transform::Transform;
+
+pub trait Renderer {
+
+    fn dimensions(&self) -> (u32, u32);
+
+    fn resize(&mut self, width: u32, height: u32);
+
+    fn register_shader(&mut self, label: &str, source: &str) -> ShaderId;
+
+    // how entities using `shader` get shaded by renderers that cannot run it;
+    // renderers that can ignore it
+    fn register_fallback(&mut self, _shader: ShaderId, _fragment: Fragment) {}
+
+    // uploads `image` for entities built with `EntityBuilder::textured`
+    fn register_texture(&mut self, image: &Image) -> TextureId;
+
+    // replaces the contents of `texture`, which may change size along with them
+    fn update_texture(&mut self, texture: TextureId, image: &Image);
+
+    // draws `frame` onto the renderer's own target
+    fn render(&mut self, frame: &Frame) -> Result<(), RenderError>;
+
+    // draws `frame` into a fresh image, regardless of target
+    fn capture(&mut self, frame: &Frame) -> Image;
+
+}
+
+// everything a renderer needs to draw one scene: the background it is cleared
+// to, the scene's entities as seen through its camera and the overlay drawn on
+// top in canvas space, both ordered back to front, and the parameters custom
+// shaders read
+pub struct Frame<'a> {
+    pub background: Background,
+    // world space => clip space
+    pub view_projection: Transform,
+    // canvas space => clip space, unaffected by the camera
+    pub screen_projection: Transform,
+    pub entities: Vec<&'a Entity>,
+    pub overlay: Vec<&'a Entity>,
+    // at most MAX_PARAMETERS, the rest read as zeros
+    pub parameters: &'a [[f32; 4]]
+}
+
+impl<'a> Frame<'a> {
+
+    // each group of entities paired with the transform it is drawn through
+    pub fn layers(&self) -> [(&[&'a Entity], &Transform); 2] {
+        [
+            (self.entities.as_slice(), &self.view_projection),
+            (self.overlay.as_slice(), &self.screen_projection)
+        ]
+    }
+
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    Surface(wgpu::SurfaceError)
+}
+
+impl std::fmt::Display for RenderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Surface(err) => write!(f, "Could not acquire the next frame: {}", err)
+        }
+    }
+}
+
+impl From<wgpu::SurfaceError> for RenderError {
+    fn from(value: wgpu::SurfaceError) -> Self {
+        Self::Surface(value)
+    }
+}
@@ -0,0 +1,251 @@
+use crate::camera::Camera;
+use crate::colors::{Color, BLACK, WHITE};
+use crate::complex::Complex;
+use crate::entities::{Entity, EntityBuilder, ShapeKind, Vertex};
+use crate::roots::{Polynomial, RootError, Roots};
+use crate::grapher::{clip, sample};
+use crate::newton;
+use crate::pipeline::ShaderId;
+use crate::stroke::{LineCap, LineJoin, Stroke};
+
+// world units (logical pixels at the default zoom) per unit of the complex plane
+const DEFAULT_SCALE: f32 = 100.0;
+// sizes in logical pixels, kept constant as the camera zooms
+const MARKER_RADIUS: f32 = 8.0;
+const MARKER_OUTLINE: f32 = 2.0;
+const AXIS_WIDTH: f32 = 1.0;
+const CURVE_WIDTH: f32 = 2.0;
+const IMAGINARY_DASH: [f32; 2] = [8.0, 6.0];
+const UNIT_CIRCLE_SEGMENTS: usize = 128;
+
+// Shows the roots of a polynomial as markers on the complex plane, over the
+// real and imaginary axes, the unit circle and the polynomial's values along the
+// real axis. Markers can be moved, which expands the polynomial again. The plane
+// can also be filled with the Newton fractal of the polynomial.
+pub struct RootPicker {
+    polynomial: Option<Polynomial>,
+    report: Option<Roots>,
+    scale: f32,
+    axis_color: Color,
+    curve_color: Color,
+    // the shader the Newton fractal is drawn with, None while it is hidden
+    fractal: Option<ShaderId>,
+    // the camera and canvas size the last entities were plotted for
+    plotted: Option<(Camera, [f32; 2])>
+}
+
+impl RootPicker {
+
+    pub fn new() -> RootPicker {
+        Self {
+            polynomial: None,
+            report: None,
+            scale: DEFAULT_SCALE,
+            axis_color: Color::new(110, 110, 110),
+            curve_color: Color::new(200, 200, 200),
+            fractal: None,
+            plotted: None
+        }
+    }
+
+    pub fn set_polynomial(&mut self, polynomial: Polynomial) -> Result<&Roots, RootError> {
+        let report = polynomial.roots()?;
+        self.polynomial = Some(polynomial);
+        self.plotted = None;
+        Ok(self.report.insert(report))
+    }
+
+    pub fn polynomial(&self) -> Option<&Polynomial> {
+        self.polynomial.as_ref()
+    }
+
+    pub fn report(&self) -> Option<&Roots> {
+        self.report.as_ref()
+    }
+
+    pub fn roots(&self) -> &[Complex] {
+        self.report.as_ref().map_or(&[], |report| report.roots.as_slice())
+    }
+
+    // places root `index` at `z` and rebuilds the polynomial around the new roots,
+    // keeping its leading coefficient
+    pub fn move_root(&mut self, index: usize, z: Complex) {
+        let (Some(polynomial), Some(report)) = (&mut self.polynomial, &mut self.report) else {
+            return;
+        };
+        report.roots[index] = z;
+        *polynomial = Polynomial::from_roots(&report.roots, polynomial.leading_coefficient());
+        report.residual = report.roots.iter().map(|&z| polynomial.evaluate(z).norm()).fold(0.0, f64::max);
+        self.plotted = None;
+    }
+
+    // the topmost marker covering `point` in world space, if any
+    pub fn marker_at(&self, point: [f32; 2], zoom: f32) -> Option<usize> {
+        let reach = (MARKER_RADIUS + MARKER_OUTLINE) / zoom;
+        self.roots().iter().rposition(|&root| {
+            let [x, y] = self.plane_to_world(root);
+            (x - point[0]).hypot(y - point[1]) <= reach
+        })
+    }
+
+    pub fn fractal(&self) -> Option<ShaderId> {
+        self.fractal
+    }
+
+    pub fn set_fractal(&mut self, shader: Option<ShaderId>) {
+        self.fractal = shader;
+        self.plotted = None;
+    }
+
+    // what the fractal shader reads, empty while the fractal is hidden or when
+    // the polynomial is of too high a degree for it
+    pub fn parameters(&self) -> Vec<[f32; 4]> {
+        match (&self.polynomial, self.fractal) {
+            (Some(polynomial), Some(_)) => {
+                newton::parameters(polynomial, self.roots(), self.scale, Self::marker_color).unwrap_or_default()
+            },
+            _ => vec![]
+        }
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+        self.plotted = None;
+    }
+
+    // evenly spread hues, so neighbouring markers never look alike
+    pub fn marker_color(index: usize) -> Color {
+        Color::from_hsv(index as f32 * 137.508, 0.75, 0.95)
+    }
+
+    pub fn plane_to_world(&self, z: Complex) -> [f32; 2] {
+        [z.re as f32 * self.scale, z.im as f32 * self.scale]
+    }
+
+    pub fn world_to_plane(&self, point: [f32; 2]) -> Complex {
+        Complex::new((point[0] / self.scale) as f64, (point[1] / self.scale) as f64)
+    }
+
+    // new entities when anything has changed since the last call, None otherwise
+    pub fn replot(&mut self, camera: &Camera, canvas_size: [f32; 2]) -> Option<Vec<Entity>> {
+        if self.plotted == Some((*camera, canvas_size)) {
+            return None;
+        }
+        self.plotted = Some((*camera, canvas_size));
+        Some(self.entities(camera, canvas_size))
+    }
+
+    // nothing until a polynomial is set
+    pub fn entities(&self, camera: &Camera, canvas_size: [f32; 2]) -> Vec<Entity> {
+
+        let polynomial = match &self.polynomial {
+            Some(polynomial) => polynomial,
+            None => return vec![]
+        };
+
+        let (min, max) = camera.visible_region(canvas_size);
+        let pixel = 1.0 / camera.zoom();
+        let mut entities = vec![];
+
+        if let Some(shader) = self.fractal.filter(|_| polynomial.degree() <= newton::MAX_DEGREE) {
+            // one quad over the whole view, colored entirely by the shader
+            let corner = |x, y| Vertex::new(x, y, 0.0, BLACK);
+            entities.push(
+                EntityBuilder::from_shape(ShapeKind::Rectangle, vec![
+                    corner(min[0], max[1]),
+                    corner(min[0], min[1]),
+                    corner(max[0], min[1]),
+                    corner(max[0], max[1])
+                ]).unwrap().shader(shader).build()
+            );
+        }
+
+        let axis = Stroke::new(AXIS_WIDTH);
+        let line = |points: Vec<[f32; 2]>, color: Color, stroke: &Stroke| {
+            let vertices = points.into_iter().map(|[x, y]| Vertex::new(x, y, 0.0, color)).collect();
+            // runs that collapse to a single point have nothing to draw
+            EntityBuilder::from_stroke(vertices, stroke, camera.zoom()).ok().map(|builder| builder.build())
+        };
+        entities.extend(line(vec![[min[0], 0.0], [max[0], 0.0]], self.axis_color, &axis));
+        entities.extend(line(vec![[0.0, min[1]], [0.0, max[1]]], self.axis_color, &axis));
+        let unit_circle = (0..=UNIT_CIRCLE_SEGMENTS)
+            .map(|i| Complex::from_polar(1.0, std::f64::consts::TAU * i as f64 / UNIT_CIRCLE_SEGMENTS as f64))
+            .map(|z| self.plane_to_world(z))
+            .collect();
+        entities.extend(line(unit_circle, self.axis_color, &axis.cap(LineCap::Round)));
+
+        // p(x) along the real axis, solid for the real part and dashed for the
+        // imaginary part, which only shows once the coefficients stop being real
+        let (low, high) = (self.world_to_plane(min), self.world_to_plane(max));
+        let margin = high.im - low.im;
+        let curve = Stroke::new(CURVE_WIDTH).join(LineJoin::Round);
+        let dashed = curve.clone().dash(IMAGINARY_DASH.to_vec(), 0.0);
+        let real: &dyn Fn(f64) -> f64 = &|x| polynomial.evaluate(Complex::from(x)).re;
+        let imaginary: &dyn Fn(f64) -> f64 = &|x| polynomial.evaluate(Complex::from(x)).im;
+        let mut parts = vec![(real, &curve)];
+        if polynomial.coefficients().iter().any(|c| c.im != 0.0) {
+            parts.push((imaginary, &dashed));
+        }
+        for (part, stroke) in parts {
+            let runs = sample(part, low.re, high.re, (pixel / self.scale) as f64);
+            for run in clip(&runs, low.im - margin, high.im + margin) {
+                let points = run.iter().map(|&[x, y]| self.plane_to_world(Complex::new(x, y))).collect();
+                entities.extend(line(points, self.curve_color, stroke));
+            }
+        }
+
+        for (index, root) in self.roots().iter().enumerate() {
+            let [x, y] = self.plane_to_world(*root);
+            let outline = EntityBuilder::from_shape(
+                ShapeKind::Circle((MARKER_RADIUS + MARKER_OUTLINE) * pixel),
+                vec![Vertex::new(x, y, 0.0, WHITE)]
+            ).unwrap().build();
+            let marker = EntityBuilder::from_shape(
+                ShapeKind::Circle(MARKER_RADIUS * pixel),
+                vec![Vertex::new(x, y, 0.0, Self::marker_color(index))]
+            ).unwrap().build();
+            entities.push(outline);
+            entities.push(marker);
+        }
+
+        entities
+    }
+
+}
+
+impl Default for RootPicker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn dragging_a_marker_moves_its_root() {
+        let mut picker = RootPicker::new();
+        // 2(z - 1)(z + 1)
+        picker.set_polynomial(Polynomial::from_real(&[-2.0, 0.0, 2.0])).unwrap();
+        let index = picker.roots().iter().position(|z| z.re > 0.0).unwrap();
+
+        // the markers keep their size on screen, covering more of the world when zoomed out
+        assert_eq!(picker.marker_at([105.0, 3.0], 1.0), Some(index));
+        assert_eq!(picker.marker_at([105.0, 3.0], 4.0), None);
+        assert_eq!(picker.marker_at([120.0, 0.0], 1.0), None);
+        assert_eq!(picker.marker_at([120.0, 0.0], 0.5), Some(index));
+
+        picker.move_root(index, Complex::new(0.0, 1.0));
+        // 2(z - i)(z + 1)
+        let expected = Polynomial::new(vec![Complex::new(0.0, -2.0), Complex::new(2.0, -2.0), Complex::from(2.0)]);
+        assert_eq!(picker.polynomial(), Some(&expected));
+        assert!(picker.report().unwrap().residual < 1e-12);
+    }
+
+}
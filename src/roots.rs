@@ -0,0 +1,229 @@
+use crate::complex::Complex;
+
+// iteration stops once no root moves further than this, relative to its size
+const TOLERANCE: f64 = 1e-12;
+const MAX_ITERATIONS: usize = 500;
+
+// coefficients are stored lowest degree first, so coefficients[i] belongs to z^i
+#[derive(Clone, Debug, PartialEq)]
+pub struct Polynomial {
+    coefficients: Vec<Complex>
+}
+
+impl Polynomial {
+
+    pub fn new(mut coefficients: Vec<Complex>) -> Polynomial {
+        while coefficients.len() > 1 && *coefficients.last().unwrap() == Complex::ZERO {
+            coefficients.pop();
+        }
+        if coefficients.is_empty() {
+            coefficients.push(Complex::ZERO);
+        }
+        Self { coefficients }
+    }
+
+    pub fn from_real(coefficients: &[f64]) -> Polynomial {
+        Self::new(coefficients.iter().map(|&c| Complex::from(c)).collect())
+    }
+
+    // leading * (z - r0)(z - r1)...
+    pub fn from_roots(roots: &[Complex], leading: Complex) -> Polynomial {
+        let mut coefficients = vec![leading];
+        for &root in roots {
+            coefficients.insert(0, Complex::ZERO);
+            for i in 0..coefficients.len() - 1 {
+                let shifted = coefficients[i + 1];
+                coefficients[i] = coefficients[i] - root * shifted;
+            }
+        }
+        Self::new(coefficients)
+    }
+
+    pub fn leading_coefficient(&self) -> Complex {
+        self.coefficients[self.degree()]
+    }
+
+    pub fn coefficients(&self) -> &[Complex] {
+        &self.coefficients
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.len() - 1
+    }
+
+    // Horner's scheme
+    pub fn evaluate(&self, z: Complex) -> Complex {
+        self.coefficients.iter().rev().fold(Complex::ZERO, |sum, &c| sum * z + c)
+    }
+
+    pub fn derivative(&self) -> Polynomial {
+        Self::new(
+            self.coefficients.iter()
+                             .enumerate()
+                             .skip(1)
+                             .map(|(power, &c)| c * Complex::from(power as f64))
+                             .collect()
+        )
+    }
+
+    // All complex roots, repeated by multiplicity, found with the Aberth-Ehrlich
+    // method: Newton steps corrected for the pull of every other root estimate.
+    pub fn roots(&self) -> Result<Roots, RootError> {
+
+        let degree = self.degree();
+        if degree == 0 {
+            return Err(RootError::Constant);
+        }
+
+        // dividing by the leading coefficient leaves the roots alone
+        let leading = self.leading_coefficient();
+        let monic = Self::new(self.coefficients.iter().map(|&c| c / leading).collect());
+        let derivative = monic.derivative();
+
+        // spread the first guesses around a circle as large as the roots are on
+        // average, turned slightly so no guess starts on a symmetry axis
+        let radius = match monic.coefficients[0].norm() {
+            0.0 => 1.0,
+            constant => constant.powf(1.0 / degree as f64)
+        };
+        let mut roots: Vec<Complex> = (0..degree)
+            .map(|k| Complex::from_polar(radius, std::f64::consts::TAU * k as f64 / degree as f64 + 0.4))
+            .collect();
+
+        let mut iterations = 0;
+        let mut converged = false;
+
+        while iterations < MAX_ITERATIONS && !converged {
+            iterations += 1;
+            converged = true;
+            for k in 0..degree {
+                let value = monic.evaluate(roots[k]);
+                if value == Complex::ZERO {
+                    continue;
+                }
+                let newton = value / derivative.evaluate(roots[k]);
+                let repulsion = (0..degree)
+                    .filter(|&j| j != k)
+                    .fold(Complex::ZERO, |sum, j| sum + Complex::ONE / (roots[k] - roots[j]));
+                let mut step = newton / (Complex::ONE - newton * repulsion);
+                if !step.is_finite() {
+                    // a flat spot or two estimates on top of each other; nudge and retry
+                    step = Complex::from_polar(radius * 1e-3, k as f64 + iterations as f64);
+                }
+                roots[k] = roots[k] - step;
+                if step.norm() > TOLERANCE * roots[k].norm().max(1.0) {
+                    converged = false;
+                }
+            }
+        }
+
+        let residual = roots.iter().map(|&z| self.evaluate(z).norm()).fold(0.0, f64::max);
+        Ok(Roots { roots, iterations, converged, residual })
+    }
+
+}
+
+impl std::fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = true;
+        for (power, c) in self.coefficients.iter().enumerate().rev() {
+            if *c == Complex::ZERO && self.degree() > 0 {
+                continue;
+            }
+            if !first {
+                write!(f, " + ")?;
+            }
+            first = false;
+            let coefficient = match (c.im == 0.0, f.precision()) {
+                (true, Some(digits)) => format!("{:.*}", digits, c.re),
+                (true, None) => format!("{}", c.re),
+                (false, Some(digits)) => format!("({:.*})", digits, c),
+                (false, None) => format!("({})", c)
+            };
+            match power {
+                0 => write!(f, "{}", coefficient)?,
+                1 => write!(f, "{}z", coefficient)?,
+                _ => write!(f, "{}z^{}", coefficient, power)?
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Roots {
+    pub roots: Vec<Complex>,
+    pub iterations: usize,
+    // false if MAX_ITERATIONS ran out first, in which case the roots are only estimates
+    pub converged: bool,
+    // largest |p(z)| over the roots found
+    pub residual: f64
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RootError {
+    // constant polynomials have no roots, or are zero everywhere
+    Constant
+}
+
+impl std::fmt::Display for RootError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Constant => write!(f, "A constant polynomial has no isolated roots")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn sorted(mut roots: Vec<Complex>) -> Vec<Complex> {
+        roots.sort_by(|a, b| a.re.partial_cmp(&b.re).unwrap().then(a.im.partial_cmp(&b.im).unwrap()));
+        roots
+    }
+
+    #[test]
+    fn finds_real_and_complex_roots() {
+        // (z - 1)(z + 2)(z^2 + 1)
+        let found = Polynomial::from_real(&[-2.0, 1.0, -1.0, 1.0, 1.0]).roots().unwrap();
+        assert!(found.converged);
+        assert!(found.residual < 1e-10);
+        let expected = [Complex::new(-2.0, 0.0), Complex::new(0.0, -1.0), Complex::new(0.0, 1.0), Complex::new(1.0, 0.0)];
+        for (root, expected) in sorted(found.roots).iter().zip(expected) {
+            assert!((*root - expected).norm() < 1e-9, "{} vs {}", root, expected);
+        }
+    }
+
+    #[test]
+    fn handles_zero_and_repeated_roots() {
+        // z^2 (z - 3)^2
+        let found = Polynomial::from_real(&[0.0, 0.0, 9.0, -6.0, 1.0]).roots().unwrap();
+        assert!(found.residual < 1e-9);
+        let roots = sorted(found.roots);
+        assert!(roots[0].norm() < 1e-6 && roots[1].norm() < 1e-6);
+        assert!((roots[2] - Complex::from(3.0)).norm() < 1e-6 && (roots[3] - Complex::from(3.0)).norm() < 1e-6);
+    }
+
+    #[test]
+    fn roots_of_unity() {
+        let found = Polynomial::from_real(&[-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0]).roots().unwrap();
+        assert!(found.converged);
+        assert!(found.roots.iter().all(|z| (z.norm() - 1.0).abs() < 1e-12));
+    }
+
+    #[test]
+    fn expands_from_roots() {
+        let roots = [Complex::new(1.0, 2.0), Complex::new(1.0, -2.0), Complex::from(-3.0)];
+        let polynomial = Polynomial::from_roots(&roots, Complex::from(2.0));
+        // 2(z^2 - 2z + 5)(z + 3)
+        assert_eq!(polynomial, Polynomial::from_real(&[30.0, -2.0, 2.0, 2.0]));
+    }
+
+    #[test]
+    fn constants_have_no_roots() {
+        assert_eq!(Polynomial::from_real(&[4.0, 0.0]).roots(), Err(RootError::Constant));
+    }
+
+}
@@ -1,5 +1,12 @@
 
 // Vertex shader
+struct Globals {
+	view_projection: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
 struct VertexIn {
 	@location(0) position: vec3<f32>,
 	@location(1) color: vec3<f32>
@@ -13,7 +20,7 @@ struct VertexOut {
 @vertex
 fn vertex_shader_main(model: VertexIn) -> VertexOut {
 	var out: VertexOut;
-	out.position = vec4<f32>(model.position, 1.0);
+	out.position = globals.view_projection * vec4<f32>(model.position, 1.0);
 	out.color = model.color;
 	return out;
 }
@@ -0,0 +1,438 @@
+use hebrides::linal::Vector;
+
+use crate::entities::Entity;
+
+// seconds of simulated time per fixed step
+const DEFAULT_TIMESTEP: f64 = 1.0 / 120.0;
+// a frame never runs more steps than this, so a long stall slows the
+// simulation down instead of freezing every frame after it
+const MAX_STEPS_PER_FRAME: usize = 8;
+// a Dormand-Prince advance gives up after this many attempted steps
+const MAX_SUBSTEPS: usize = 10_000;
+
+// the state of a system of ordinary differential equations
+pub type State = Vector<f64>;
+
+fn norm(state: &State) -> f64 {
+    state.dot(state).sqrt()
+}
+
+// y + dt * (c1 k1 + c2 k2 + ...)
+fn combine(state: &State, dt: f64, terms: &[(f64, &State)]) -> State {
+    terms.iter()
+         .filter(|(c, _)| *c != 0.0)
+         .fold(state.clone(), |sum, (c, k)| sum + (*k).clone() * (dt * c))
+}
+
+// y' = f(t, y), one explicit Euler step
+pub fn euler<F: Fn(f64, &State) -> State>(f: &F, t: f64, y: &State, dt: f64) -> State {
+    combine(y, dt, &[(1.0, &f(t, y))])
+}
+
+// the classic fourth order Runge-Kutta step
+pub fn rk4<F: Fn(f64, &State) -> State>(f: &F, t: f64, y: &State, dt: f64) -> State {
+    let k1 = f(t, y);
+    let k2 = f(t + dt / 2.0, &combine(y, dt, &[(0.5, &k1)]));
+    let k3 = f(t + dt / 2.0, &combine(y, dt, &[(0.5, &k2)]));
+    let k4 = f(t + dt, &combine(y, dt, &[(1.0, &k3)]));
+    combine(y, dt, &[(1.0 / 6.0, &k1), (1.0 / 3.0, &k2), (1.0 / 3.0, &k3), (1.0 / 6.0, &k4)])
+}
+
+// x'' = a(x), one velocity Verlet step. Symplectic, so the energy of a
+// conservative system wobbles around its true value instead of drifting.
+pub fn verlet<A: Fn(&State) -> State>(acceleration: &A, x: &State, v: &State, dt: f64) -> (State, State) {
+    let a = acceleration(x);
+    let x = combine(x, dt, &[(1.0, v), (dt / 2.0, &a)]);
+    let v = combine(v, dt, &[(0.5, &a), (0.5, &acceleration(&x))]);
+    (x, v)
+}
+
+// Dormand-Prince tableau: nodes, then the stages' weights, then the fifth
+// order solution and the difference to its embedded fourth order one
+const DP_NODES: [f64; 6] = [1.0 / 5.0, 3.0 / 10.0, 4.0 / 5.0, 8.0 / 9.0, 1.0, 1.0];
+const DP_WEIGHTS: [&[f64]; 6] = [
+    &[1.0 / 5.0],
+    &[3.0 / 40.0, 9.0 / 40.0],
+    &[44.0 / 45.0, -56.0 / 15.0, 32.0 / 9.0],
+    &[19372.0 / 6561.0, -25360.0 / 2187.0, 64448.0 / 6561.0, -212.0 / 729.0],
+    &[9017.0 / 3168.0, -355.0 / 33.0, 46732.0 / 5247.0, 49.0 / 176.0, -5103.0 / 18656.0],
+    &[35.0 / 384.0, 0.0, 500.0 / 1113.0, 125.0 / 192.0, -2187.0 / 6784.0, 11.0 / 84.0]
+];
+const DP_ERROR: [f64; 7] = [
+    35.0 / 384.0 - 5179.0 / 57600.0,
+    0.0,
+    500.0 / 1113.0 - 7571.0 / 16695.0,
+    125.0 / 192.0 - 393.0 / 640.0,
+    -2187.0 / 6784.0 + 92097.0 / 339200.0,
+    11.0 / 84.0 - 187.0 / 2100.0,
+    -1.0 / 40.0
+];
+
+// Adaptive Runge-Kutta 4(5): every step is taken at fifth order and checked
+// against the embedded fourth order solution, growing or shrinking the step
+// until the difference stays within `tolerance`, relative to the size of the
+// state once that exceeds 1.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DormandPrince {
+    pub tolerance: f64,
+    pub min_step: f64,
+    pub max_substeps: usize
+}
+
+impl DormandPrince {
+
+    pub fn new(tolerance: f64) -> DormandPrince {
+        Self { tolerance, min_step: 1e-9, max_substeps: MAX_SUBSTEPS }
+    }
+
+    // the fifth order step and its error, in units of the tolerance
+    pub fn attempt<F: Fn(f64, &State) -> State>(&self, f: &F, t: f64, y: &State, dt: f64) -> (State, f64) {
+        let mut stages = vec![f(t, y)];
+        for (node, weights) in DP_NODES.iter().zip(DP_WEIGHTS) {
+            let terms: Vec<(f64, &State)> = weights.iter().copied().zip(&stages).collect();
+            let next = f(t + node * dt, &combine(y, dt, &terms));
+            stages.push(next);
+        }
+        // the last stage is evaluated at the solution itself
+        let solution = combine(y, dt, &DP_WEIGHTS[5].iter().copied().zip(&stages).collect::<Vec<_>>());
+        let zero = y.clone() * 0.0;
+        let difference = combine(&zero, dt, &DP_ERROR.iter().copied().zip(&stages).collect::<Vec<_>>());
+        let error = norm(&difference) / (self.tolerance * norm(&solution).max(1.0));
+        (solution, error)
+    }
+
+    // Advances `y` by `duration`, in as many steps as the tolerance asks for
+    // up to `max_substeps`, returning the state reached and how much of
+    // `duration` was left when they ran out. `step` is the first step tried and
+    // is left holding the size the next call should start with.
+    pub fn advance<F: Fn(f64, &State) -> State>(&self, f: &F, t: f64, y: &State, duration: f64, step: &mut f64) -> (State, f64) {
+        let end = t + duration;
+        let mut t = t;
+        let mut y = y.clone();
+        for _ in 0..self.max_substeps {
+            if t >= end {
+                break;
+            }
+            let dt = step.min(end - t);
+            let (next, error) = self.attempt(f, t, &y, dt);
+            if error <= 1.0 || dt <= self.min_step {
+                t += dt;
+                y = next;
+            }
+            // the error shrinks with the fifth power of the step
+            let factor = if error == 0.0 { 5.0 } else { (0.9 * error.powf(-0.2)).clamp(0.2, 5.0) };
+            // a step shortened only to land on `end` says nothing about the next one
+            if dt == *step || factor < 1.0 {
+                *step = (dt * factor).max(self.min_step);
+            }
+        }
+        (y, (end - t).max(0.0))
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Integrator {
+    Euler,
+    RungeKutta4,
+    // carries the step size the next fixed step starts adapting from
+    DormandPrince(DormandPrince, f64),
+    // for states holding this many positions followed by their velocities,
+    // whose derivatives are the velocities followed by the accelerations
+    Verlet(usize)
+}
+
+impl Integrator {
+
+    pub fn dormand_prince(tolerance: f64) -> Integrator {
+        Self::DormandPrince(DormandPrince::new(tolerance), DEFAULT_TIMESTEP)
+    }
+
+    pub fn step<F: Fn(f64, &State) -> State>(&mut self, f: &F, t: f64, y: &State, dt: f64) -> State {
+        match self {
+            Self::Euler => euler(f, t, y, dt),
+            Self::RungeKutta4 => rk4(f, t, y, dt),
+            // whatever the step budget leaves undone is dropped, slowing the
+            // system down rather than stalling the frame
+            Self::DormandPrince(method, step) => method.advance(f, t, y, dt, step).0,
+            Self::Verlet(dimensions) => {
+                let n = *dimensions;
+                let half = |y: &State, from: usize| Vector::new((from..from + n).map(|i| y[i]).collect());
+                let join = |x: &State, v: &State| Vector::new((0..n).map(|i| x[i]).chain((0..n).map(|i| v[i])).collect());
+                let v = half(y, n);
+                // forces are taken at the start of the step, so only ones that
+                // depend on the positions alone are integrated exactly
+                let acceleration = |x: &State| half(&f(t, &join(x, &v)), n);
+                let (x, v) = verlet(&acceleration, &half(y, 0), &v, dt);
+                join(&x, &v)
+            }
+        }
+    }
+
+}
+
+// Turns the real time between frames into a whole number of fixed steps,
+// carrying the remainder over to the next frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FixedTimestep {
+    step: f64,
+    accumulated: f64
+}
+
+impl FixedTimestep {
+
+    pub fn new(step: f64) -> FixedTimestep {
+        if !(step > 0.0 && step.is_finite()) {
+            panic!("fixed timestep must be positive and finite, not {}", step)
+        }
+        Self { step, accumulated: 0.0 }
+    }
+
+    pub fn step(&self) -> f64 {
+        self.step
+    }
+
+    // how many steps `elapsed` seconds are worth, at most MAX_STEPS_PER_FRAME
+    pub fn advance(&mut self, elapsed: f64) -> usize {
+        self.accumulated += elapsed;
+        let steps = (self.accumulated / self.step).floor() as usize;
+        self.accumulated -= steps as f64 * self.step;
+        if steps > MAX_STEPS_PER_FRAME {
+            // too far behind to catch up, drop the backlog
+            self.accumulated = 0.0;
+        }
+        steps.min(MAX_STEPS_PER_FRAME)
+    }
+
+    // how far into the next step the accumulated time reaches, from 0 to 1
+    pub fn progress(&self) -> f64 {
+        self.accumulated / self.step
+    }
+
+}
+
+// anything the Simulation scene advances in fixed steps and draws
+pub trait Simulated {
+
+    fn step(&mut self, t: f64, dt: f64);
+
+    fn entities(&self) -> Vec<Entity>;
+
+    // brings `entities`, as this system last drew them, up to date
+    fn redraw(&self, entities: &mut Vec<Entity>) {
+        *entities = self.entities();
+    }
+
+}
+
+type Derivative = Box<dyn Fn(f64, &State) -> State>;
+type Draw = Box<dyn Fn(&State) -> Vec<Entity>>;
+
+// A first order system y' = f(t, y) advanced by one of the integrators and drawn
+// from its state by `draw`.
+pub struct OdeSystem {
+    derivative: Derivative,
+    state: State,
+    integrator: Integrator,
+    draw: Draw
+}
+
+impl OdeSystem {
+
+    pub fn new<F, D>(derivative: F, state: State, integrator: Integrator, draw: D) -> OdeSystem
+    where F: Fn(f64, &State) -> State + 'static, D: Fn(&State) -> Vec<Entity> + 'static {
+        Self {
+            derivative: Box::new(derivative),
+            state,
+            integrator,
+            draw: Box::new(draw)
+        }
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+}
+
+impl Simulated for OdeSystem {
+
+    fn step(&mut self, t: f64, dt: f64) {
+        self.state = self.integrator.step(&self.derivative, t, &self.state, dt);
+    }
+
+    fn entities(&self) -> Vec<Entity> {
+        (self.draw)(&self.state)
+    }
+
+}
+
+// everything the Simulation scene runs, with the clock driving it
+pub struct Simulation {
+    clock: FixedTimestep,
+    time: f64,
+    systems: Vec<Box<dyn Simulated>>,
+    // how many entities each system drew last time
+    drawn: Vec<usize>,
+    // whether anything moved since the entities were last drawn
+    moved: bool
+}
+
+impl Simulation {
+
+    pub fn new() -> Simulation {
+        Self {
+            clock: FixedTimestep::new(DEFAULT_TIMESTEP),
+            time: 0.0,
+            systems: vec![],
+            drawn: vec![],
+            moved: true
+        }
+    }
+
+    pub fn add<S: Simulated + 'static>(&mut self, system: S) {
+        self.systems.push(Box::new(system));
+        self.drawn.push(0);
+        self.moved = true;
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn timestep(&self) -> f64 {
+        self.clock.step()
+    }
+
+    pub fn set_timestep(&mut self, step: f64) {
+        self.clock = FixedTimestep::new(step);
+    }
+
+    // runs as many fixed steps as `elapsed` seconds of real time call for
+    pub fn advance(&mut self, elapsed: f64) {
+        for _ in 0..self.clock.advance(elapsed) {
+            self.step();
+        }
+    }
+
+    pub fn step(&mut self) {
+        let dt = self.clock.step();
+        for system in &mut self.systems {
+            system.step(self.time, dt);
+        }
+        self.time += dt;
+        self.moved = true;
+    }
+
+    // brings `entities`, as drawn by the last call, up to date with anything
+    // that has moved since, letting each system reuse what it drew before
+    pub fn redraw(&mut self, entities: &mut Vec<Entity>) {
+        if self.drawn.iter().sum::<usize>() != entities.len() {
+            entities.clear();
+            self.drawn.iter_mut().for_each(|count| *count = 0);
+            self.moved = true;
+        }
+        if !self.moved {
+            return;
+        }
+        self.moved = false;
+        let mut start = 0;
+        for (system, count) in self.systems.iter().zip(&mut self.drawn) {
+            let mut own: Vec<Entity> = entities.splice(start..start + *count, []).collect();
+            system.redraw(&mut own);
+            *count = own.len();
+            entities.splice(start..start, own);
+            start += *count;
+        }
+    }
+
+    pub fn entities(&self) -> Vec<Entity> {
+        self.systems.iter().flat_map(|system| system.entities()).collect()
+    }
+
+}
+
+impl Default for Simulation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn growth(_: f64, y: &State) -> State {
+        y.clone()
+    }
+
+    #[test]
+    fn runge_kutta_orders() {
+        // y' = y from y(0) = 1 over [0, 1], so y(1) = e
+        let e = std::f64::consts::E;
+        let error = |stepper: &dyn Fn(&State, f64) -> State, steps: usize| {
+            let dt = 1.0 / steps as f64;
+            let y = (0..steps).fold(Vector::new(vec![1.0]), |y, _| stepper(&y, dt));
+            (y[0] - e).abs()
+        };
+        let euler_step = |y: &State, dt| euler(&growth, 0.0, y, dt);
+        let rk4_step = |y: &State, dt| rk4(&growth, 0.0, y, dt);
+        // halving the step halves Euler's error and cuts RK4's sixteenfold
+        assert!((error(&euler_step, 100) / error(&euler_step, 200) - 2.0).abs() < 0.05);
+        assert!((error(&rk4_step, 10) / error(&rk4_step, 20) - 16.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn dormand_prince_meets_its_tolerance() {
+        let method = DormandPrince::new(1e-9);
+        let mut step = 0.5;
+        let (y, remaining) = method.advance(&growth, 0.0, &Vector::new(vec![1.0]), 1.0, &mut step);
+        assert!((y[0] - std::f64::consts::E).abs() < 1e-8);
+        assert_eq!(remaining, 0.0);
+        // the step settles well below the first guess
+        assert!(step < 0.5);
+    }
+
+    #[test]
+    fn dormand_prince_stops_at_its_step_budget() {
+        let method = DormandPrince { max_substeps: 4, ..DormandPrince::new(1e-9) };
+        let mut step = 0.01;
+        let (_, remaining) = method.advance(&growth, 0.0, &Vector::new(vec![1.0]), 1.0, &mut step);
+        assert!(remaining > 0.5 && remaining < 1.0);
+    }
+
+    #[test]
+    fn verlet_conserves_energy() {
+        // x'' = -x, whose energy (x^2 + v^2) / 2 is constant
+        let spring = |_: f64, y: &State| Vector::new(vec![y[1], -y[0]]);
+        let mut system = OdeSystem::new(spring, Vector::new(vec![1.0, 0.0]), Integrator::Verlet(1), |_: &State| vec![]);
+        for step in 0..100_000 {
+            system.step(step as f64 * 0.05, 0.05);
+        }
+        let [x, v] = [system.state()[0], system.state()[1]];
+        assert!(((x * x + v * v) / 2.0 - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn fixed_timestep_carries_the_remainder() {
+        let mut clock = FixedTimestep::new(0.25);
+        assert_eq!(clock.advance(0.625), 2);
+        assert_eq!(clock.progress(), 0.5);
+        assert_eq!(clock.advance(0.125), 1);
+        // a stall drops the steps it cannot make up
+        assert_eq!(clock.advance(100.0), MAX_STEPS_PER_FRAME);
+        assert_eq!(clock.progress(), 0.0);
+    }
+
+    #[test]
+    fn timesteps_must_be_positive_and_finite() {
+        for step in [0.0, -0.25, f64::NAN, f64::INFINITY] {
+            assert!(std::panic::catch_unwind(|| FixedTimestep::new(step)).is_err());
+        }
+        let mut simulation = Simulation::new();
+        simulation.set_timestep(0.5);
+        assert_eq!(simulation.timestep(), 0.5);
+    }
+
+}
@@ -0,0 +1,330 @@
+use winit::event::VirtualKeyCode;
+
+use crate::colors::{Color, BLUE, WHITE};
+use crate::entities::{Entity, EntityBuilder, ShapeKind, Vertex};
+use crate::marlin::{MasterWindowState, SceneName};
+use crate::pipeline::TextureId;
+use crate::text::{Text, Typesetter};
+
+const TRACK_WIDTH: f32 = 4.0;
+const THUMB_RADIUS: f32 = 8.0;
+const FOCUS_RING: f32 = 3.0;
+const LABEL_SIZE: f32 = 16.0;
+const LABEL_GAP: f32 = 4.0;
+// share of the range a key press moves sliders without a step
+const CONTINUOUS_NUDGE: f64 = 0.01;
+const PAGE_NUDGES: f64 = 10.0;
+const MAX_DECIMALS: usize = 6;
+
+// which of a MasterWindowState's sliders, in the order they were added
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SliderId(pub(crate) usize);
+
+type OnChange = Box<dyn FnMut(&mut MasterWindowState, f64)>;
+
+// A horizontal track in canvas coordinates from `min` at `origin` to `max`
+// `length` pixels to the right, snapping to multiples of `step` unless it is 0
+pub struct Slider {
+    inhabiting_scene: SceneName,
+    origin: [f32; 2],
+    length: f32,
+    min: f64,
+    max: f64,
+    step: f64,
+    value: f64,
+    track_color: Color,
+    fill_color: Color,
+    thumb_color: Color,
+    label: Option<String>,
+    focused: bool,
+    // None only while it is running
+    on_change: Option<OnChange>,
+    entities: Vec<Entity>,
+    // None until the label has been typeset for the current value
+    label_entity: Option<Option<Entity>>
+}
+
+impl Slider {
+
+    pub fn new(inhabiting_scene: SceneName, origin: [f32; 2], length: f32, min: f64, max: f64) -> Slider {
+        let mut slider = Self {
+            inhabiting_scene,
+            origin,
+            length,
+            min,
+            max,
+            step: 0.0,
+            value: min,
+            track_color: Color::new(90, 90, 90),
+            fill_color: BLUE,
+            thumb_color: WHITE,
+            label: None,
+            focused: false,
+            on_change: None,
+            entities: vec![],
+            label_entity: None
+        };
+        slider.rebuild();
+        slider
+    }
+
+    pub fn step(mut self, step: f64) -> Slider {
+        self.step = step;
+        self.value = self.snap(self.value);
+        self.rebuild();
+        self
+    }
+
+    // the starting value; unlike `set_value`, without calling back
+    pub fn initial(mut self, value: f64) -> Slider {
+        self.value = self.snap(value);
+        self.rebuild();
+        self
+    }
+
+    pub fn colors(mut self, track: Color, fill: Color, thumb: Color) -> Slider {
+        (self.track_color, self.fill_color, self.thumb_color) = (track, fill, thumb);
+        self.rebuild();
+        self
+    }
+
+    pub fn label(mut self, label: &str) -> Slider {
+        self.label = Some(label.to_string());
+        self.label_entity = None;
+        self
+    }
+
+    // called with the new value whenever the user moves the slider
+    pub fn on_change<F: FnMut(&mut MasterWindowState, f64) + 'static>(mut self, on_change: F) -> Slider {
+        self.on_change = Some(Box::new(on_change));
+        self
+    }
+
+    pub fn scene(&self) -> SceneName {
+        self.inhabiting_scene
+    }
+
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    pub fn range(&self) -> (f64, f64) {
+        (self.min, self.max)
+    }
+
+    // whether the value changed once clamped to the range and snapped to a step
+    pub fn set_value(&mut self, value: f64) -> bool {
+        let value = self.snap(value);
+        if value == self.value {
+            return false;
+        }
+        self.value = value;
+        self.rebuild();
+        true
+    }
+
+    fn snap(&self, value: f64) -> f64 {
+        let (low, high) = (self.min.min(self.max), self.min.max(self.max));
+        let snapped = if self.step > 0.0 {
+            self.min + ((value - self.min) / self.step).round() * self.step
+        } else {
+            value
+        };
+        snapped.clamp(low, high)
+    }
+
+    pub fn is_focused(&self) -> bool {
+        self.focused
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        if self.focused != focused {
+            self.focused = focused;
+            self.rebuild();
+        }
+    }
+
+    // `point` in canvas coordinates, anywhere along the track or on the thumb
+    pub fn contains(&self, point: [f32; 2]) -> bool {
+        let [x, y] = self.origin;
+        point[0] >= x - THUMB_RADIUS && point[0] <= x + self.length + THUMB_RADIUS && (point[1] - y).abs() <= THUMB_RADIUS
+    }
+
+    // the value under a canvas x coordinate, before snapping; a track without
+    // length only has its minimum to offer
+    pub fn value_at(&self, x: f32) -> f64 {
+        if self.length <= 0.0 {
+            return self.min;
+        }
+        let along = ((x - self.origin[0]) / self.length) as f64;
+        self.min + along * (self.max - self.min)
+    }
+
+    fn thumb_x(&self) -> f32 {
+        let along = if self.max == self.min { 0.0 } else { (self.value - self.min) / (self.max - self.min) };
+        self.origin[0] + along as f32 * self.length
+    }
+
+    // the value `key` moves the slider to, if it is one sliders respond to
+    pub fn nudge(&self, key: VirtualKeyCode) -> Option<f64> {
+        let increment = if self.step > 0.0 { self.step } else { CONTINUOUS_NUDGE * (self.max - self.min) };
+        match key {
+            VirtualKeyCode::Left | VirtualKeyCode::Down => Some(self.value - increment),
+            VirtualKeyCode::Right | VirtualKeyCode::Up => Some(self.value + increment),
+            VirtualKeyCode::PageDown => Some(self.value - PAGE_NUDGES * increment),
+            VirtualKeyCode::PageUp => Some(self.value + PAGE_NUDGES * increment),
+            VirtualKeyCode::Home => Some(self.min),
+            VirtualKeyCode::End => Some(self.max),
+            _ => None
+        }
+    }
+
+    // lends the callback out to be run against the state that owns the slider
+    pub fn take_on_change(&mut self) -> Option<OnChange> {
+        self.on_change.take()
+    }
+
+    // hands a lent callback back, unless it was replaced while it ran
+    pub fn return_on_change(&mut self, on_change: OnChange) {
+        self.on_change.get_or_insert(on_change);
+    }
+
+    fn rebuild(&mut self) {
+
+        let [x, y] = self.origin;
+        let thumb = self.thumb_x();
+        let bar = |left: f32, right: f32, color: Color| {
+            let half = TRACK_WIDTH / 2.0;
+            let corner = |x: f32, y: f32| Vertex::new(x, y, 0.0, color);
+            EntityBuilder::from_shape(ShapeKind::Rectangle, vec![
+                corner(left, y + half),
+                corner(left, y - half),
+                corner(right, y - half),
+                corner(right, y + half)
+            ]).unwrap().build()
+        };
+        let disc = |radius: f32, color: Color| {
+            EntityBuilder::from_shape(ShapeKind::Circle(radius), vec![Vertex::new(thumb, y, 0.0, color)]).unwrap().build()
+        };
+
+        self.entities = vec![bar(x, x + self.length, self.track_color)];
+        if thumb > x {
+            self.entities.push(bar(x, thumb, self.fill_color));
+        }
+        if self.focused {
+            self.entities.push(disc(THUMB_RADIUS + FOCUS_RING, self.fill_color));
+        }
+        self.entities.push(disc(THUMB_RADIUS, self.thumb_color));
+        self.label_entity = None;
+    }
+
+    // enough decimals to tell neighbouring steps apart
+    fn readout(&self) -> String {
+        let exact = |decimals: &usize| {
+            let scaled = self.step * 10f64.powi(*decimals as i32);
+            (scaled - scaled.round()).abs() < 1e-9
+        };
+        let decimals = if self.step > 0.0 { (0..MAX_DECIMALS).find(exact).unwrap_or(MAX_DECIMALS) } else { 2 };
+        format!("{:.*}", decimals, self.value)
+    }
+
+    // brings the label up to date with the value; `texture` must hold the
+    // typesetter's atlas
+    pub fn typeset(&mut self, typesetter: &mut Typesetter, texture: TextureId) {
+        if self.label_entity.is_some() {
+            return;
+        }
+        let [x, y] = self.origin;
+        let label = self.label.as_ref().and_then(|label| {
+            let text = Text::new(&format!("{}: {}", label, self.readout()), LABEL_SIZE).color(self.thumb_color);
+            let top = y + THUMB_RADIUS + LABEL_GAP + typesetter.measure(&text)[1];
+            typesetter.entity(&text, [x, top], texture)
+        });
+        self.label_entity = Some(label);
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = &Entity> {
+        self.entities.iter().chain(self.label_entity.iter().flatten())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use winit::event::ElementState;
+
+    #[test]
+    fn values_snap_to_steps_within_the_range() {
+        let mut slider = Slider::new(SceneName::Home, [0.0, 0.0], 100.0, 1.0, 2.0).step(0.25);
+        assert!(slider.set_value(1.3));
+        assert_eq!(slider.value(), 1.25);
+        assert!(!slider.set_value(1.2));
+        assert!(slider.set_value(7.0));
+        assert_eq!(slider.value(), 2.0);
+        assert_eq!(slider.value_at(50.0), 1.5);
+        assert_eq!(slider.readout(), "2.00");
+    }
+
+    #[test]
+    fn tracks_without_length_stay_at_their_minimum() {
+        for length in [0.0, -20.0] {
+            let slider = Slider::new(SceneName::Home, [10.0, 0.0], length, 1.0, 2.0);
+            assert_eq!(slider.value_at(10.0), 1.0);
+            assert_eq!(slider.value_at(-5.0), 1.0);
+        }
+    }
+
+    #[test]
+    fn keys_nudge_by_a_step() {
+        let stepped = Slider::new(SceneName::Home, [0.0, 0.0], 100.0, 0.0, 10.0).step(2.0).initial(4.0);
+        assert_eq!(stepped.nudge(VirtualKeyCode::Right), Some(6.0));
+        assert_eq!(stepped.nudge(VirtualKeyCode::PageDown), Some(-16.0));
+        assert_eq!(stepped.nudge(VirtualKeyCode::End), Some(10.0));
+        assert_eq!(stepped.nudge(VirtualKeyCode::Space), None);
+        let continuous = Slider::new(SceneName::Home, [0.0, 0.0], 100.0, 0.0, 50.0);
+        assert_eq!(continuous.nudge(VirtualKeyCode::Up), Some(0.5));
+    }
+
+    #[test]
+    fn the_thumb_follows_the_value() {
+        let mut slider = Slider::new(SceneName::Home, [-50.0, 20.0], 100.0, 0.0, 1.0);
+        // just the track and the thumb at its left end
+        assert_eq!(slider.entities().count(), 2);
+        slider.set_value(0.75);
+        slider.set_focused(true);
+        assert_eq!(slider.entities().count(), 4);
+        let thumb = slider.entities().last().unwrap();
+        let right = thumb.vertices.iter().map(|v| v.position[0]).fold(f32::NEG_INFINITY, f32::max);
+        assert_eq!(right, 25.0 + THUMB_RADIUS);
+        assert!(slider.contains([25.0, 20.0 + THUMB_RADIUS]) && !slider.contains([25.0, 30.0]));
+    }
+
+    #[test]
+    fn sliders_drag_and_nudge() {
+        let mut state = MasterWindowState::software(160, 120);
+        let changes = Rc::new(RefCell::new(vec![]));
+        let recorded = changes.clone();
+        let slider = state.add_slider(
+            Slider::new(SceneName::Home, [-60.0, -20.0], 120.0, 0.0, 10.0)
+                .step(1.0)
+                .on_change(move |_, value| recorded.borrow_mut().push(value))
+        );
+        state.move_cursor([-60.0, -20.0]);
+        state.mouse_button(ElementState::Pressed);
+        assert!(state.slider(slider).is_focused());
+        // rounds to the nearest step as it goes
+        state.move_cursor([-5.0, -25.0]);
+        state.mouse_button(ElementState::Released);
+        state.press_key(VirtualKeyCode::Right);
+        state.press_key(VirtualKeyCode::Right);
+        assert_eq!(state.slider(slider).value(), 7.0);
+        assert_eq!(*changes.borrow(), vec![5.0, 6.0, 7.0]);
+    }
+
+}
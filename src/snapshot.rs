@@ -0,0 +1,423 @@
+use std::path::PathBuf;
+
+use crate::image::Image;
+
+// Golden-image comparisons for rendered scenes. References live in
+// `snapshots/<name>.png`; a failing comparison leaves the rendered frame and a
+// diff next to each other in `target/snapshots/`. Running the tests with
+// MARLIN_BLESS=1 records new references.
+
+const BLESS_VARIABLE: &str = "MARLIN_BLESS";
+
+pub struct Comparison {
+    pub mismatched_pixels: usize,
+    pub diff: Image
+}
+
+impl Comparison {
+
+    pub fn matches(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+
+}
+
+// a pixel matches when none of its channels differ by more than `tolerance`
+pub fn compare(actual: &Image, expected: &Image, tolerance: u8) -> Comparison {
+
+    if actual.width() != expected.width() || actual.height() != expected.height() {
+        panic!(
+            "rendered image is {}x{} but the reference is {}x{}",
+            actual.width(), actual.height(), expected.width(), expected.height()
+        )
+    }
+
+    let mut diff = Image::new(actual.width(), actual.height());
+    let mut mismatched_pixels = 0;
+
+    for y in 0..actual.height() {
+        for x in 0..actual.width() {
+            let rendered = actual.pixel(x, y);
+            let reference = expected.pixel(x, y);
+            let within_tolerance = rendered.iter()
+                                           .zip(reference)
+                                           .all(|(&a, b)| a.abs_diff(b) <= tolerance);
+            if within_tolerance {
+                // faded copy of the reference so mismatches stand out
+                let luma = ((reference[0] as u32 + reference[1] as u32 + reference[2] as u32) / 3) as u8;
+                diff.set_pixel(x, y, [luma / 4, luma / 4, luma / 4, 255]);
+            } else {
+                mismatched_pixels += 1;
+                diff.set_pixel(x, y, [255, 0, 0, 255]);
+            }
+        }
+    }
+
+    Comparison { mismatched_pixels, diff }
+}
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("snapshots").join(format!("{}.png", name))
+}
+
+fn failure_directory() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target").join("snapshots")
+}
+
+pub fn assert_snapshot(name: &str, actual: &Image, tolerance: u8) {
+
+    let reference = reference_path(name);
+
+    if std::env::var_os(BLESS_VARIABLE).is_some() {
+        std::fs::create_dir_all(reference.parent().unwrap()).unwrap();
+        actual.save_png(&reference).unwrap();
+        return;
+    }
+
+    if !reference.exists() {
+        panic!("missing reference {} for snapshot {}; run with {}=1 to record it", reference.display(), name, BLESS_VARIABLE)
+    }
+
+    let expected = Image::load_png(&reference).unwrap();
+    let comparison = compare(actual, &expected, tolerance);
+
+    if comparison.matches() {
+        return;
+    }
+
+    let failures = failure_directory();
+    std::fs::create_dir_all(&failures).unwrap();
+    let actual_path = failures.join(format!("{}.actual.png", name));
+    let diff_path = failures.join(format!("{}.diff.png", name));
+    actual.save_png(&actual_path).unwrap();
+    comparison.diff.save_png(&diff_path).unwrap();
+
+    panic!(
+        "snapshot {} differs from {} in {} pixels (tolerance {}); see {} and {}",
+        name,
+        reference.display(),
+        comparison.mismatched_pixels,
+        tolerance,
+        actual_path.display(),
+        diff_path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use hebrides::linal::Vector;
+
+    use crate::colors::{Background, BLACK, BLUE, RED, WHITE};
+    use crate::entities::{EntityBuilder, ShapeKind, Vertex};
+    use crate::marlin::{MasterWindowState, SceneName};
+    use crate::particles::{Particle, ParticleSystem};
+    use crate::physics::{Body, World};
+    use crate::pipeline::TextureId;
+    use crate::roots::Polynomial;
+    use crate::slider::Slider;
+    use crate::simulation::{Integrator, OdeSystem, State};
+    use crate::stroke::{LineCap, LineJoin, Stroke};
+    use crate::text::{Align, Text};
+
+    const WIDTH: u32 = 160;
+    const HEIGHT: u32 = 120;
+    const TOLERANCE: u8 = 2;
+
+    fn state() -> MasterWindowState {
+        MasterWindowState::software(WIDTH, HEIGHT)
+    }
+
+    #[test]
+    fn comparison_respects_tolerance() {
+        let mut expected = Image::new(2, 1);
+        expected.set_pixel(0, 0, [100, 100, 100, 255]);
+        expected.set_pixel(1, 0, [100, 100, 100, 255]);
+        let mut actual = expected.clone();
+        actual.set_pixel(0, 0, [102, 98, 100, 255]);
+        actual.set_pixel(1, 0, [103, 100, 100, 255]);
+
+        let comparison = compare(&actual, &expected, 2);
+
+        assert_eq!(comparison.mismatched_pixels, 1);
+        assert_eq!(comparison.diff.pixel(1, 0), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn triangle() {
+        let mut state = state();
+        state.add_shape(&SceneName::Home, &ShapeKind::Triangle, vec![
+            Vertex::new(-30.0, -20.0, 0.0, RED),
+            Vertex::new(30.0, -20.0, 0.0, BLUE),
+            Vertex::new(0.0, 25.0, 0.0, WHITE)
+        ]);
+        assert_snapshot("triangle", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn rectangle() {
+        let mut state = state();
+        state.add_shape(&SceneName::Home, &ShapeKind::Rectangle, vec![
+            Vertex::new(-25.0, 15.0, 0.0, BLUE),
+            Vertex::new(-25.0, -15.0, 0.0, BLUE),
+            Vertex::new(25.0, -15.0, 0.0, BLUE),
+            Vertex::new(25.0, 15.0, 0.0, BLUE)
+        ]);
+        assert_snapshot("rectangle", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn circle() {
+        let mut state = state();
+        state.add_shape(&SceneName::Home, &ShapeKind::Circle(20.0), vec![Vertex::new(5.0, -2.5, 0.0, RED)]);
+        assert_snapshot("circle", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn concave_polygon() {
+        let mut state = state();
+        // an arrow pointing right, wound clockwise
+        state.add_shape(&SceneName::Home, &ShapeKind::Polygon, vec![
+            Vertex::new(-40.0, 10.0, 0.0, WHITE),
+            Vertex::new(10.0, 10.0, 0.0, WHITE),
+            Vertex::new(10.0, 30.0, 0.0, RED),
+            Vertex::new(45.0, 0.0, 0.0, RED),
+            Vertex::new(10.0, -30.0, 0.0, RED),
+            Vertex::new(10.0, -10.0, 0.0, WHITE),
+            Vertex::new(-40.0, -10.0, 0.0, WHITE)
+        ]);
+        assert_snapshot("concave_polygon", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn joins_caps_and_dashes() {
+        let mut state = state();
+        let zigzag = |y: f32, color| vec![
+            Vertex::new(-60.0, y, 0.0, color),
+            Vertex::new(-30.0, y + 20.0, 0.0, color),
+            Vertex::new(0.0, y, 0.0, color),
+            Vertex::new(30.0, y + 20.0, 0.0, color),
+            Vertex::new(60.0, y, 0.0, color)
+        ];
+        state.add_line(&SceneName::Home, zigzag(25.0, WHITE), &Stroke::new(8.0).join(LineJoin::Miter).cap(LineCap::Butt));
+        state.add_line(&SceneName::Home, zigzag(-10.0, RED), &Stroke::new(8.0).join(LineJoin::Round).cap(LineCap::Round));
+        state.add_line(&SceneName::Home, zigzag(-45.0, BLUE), &Stroke::new(6.0).join(LineJoin::Bevel).cap(LineCap::Square).dash(vec![10.0, 12.0], 0.0));
+        assert_snapshot("joins_caps_and_dashes", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn grapher_splits_curves_at_poles() {
+        let mut state = state();
+        state.grapher_mut().set_scale(20.0);
+        state.grapher_mut().plot(|x| x.sin(), BLUE);
+        state.grapher_mut().plot(|x| 1.0 / x, RED);
+        assert_snapshot("grapher", &state.capture_scene(SceneName::Grapher), TOLERANCE);
+    }
+
+    #[test]
+    fn root_markers() {
+        let mut state = state();
+        state.root_picker_mut().set_scale(40.0);
+        // z^3 - 1
+        state.root_picker_mut().set_polynomial(Polynomial::from_real(&[-1.0, 0.0, 0.0, 1.0])).unwrap();
+        assert_snapshot("root_markers", &state.capture_scene(SceneName::RootPicker), TOLERANCE);
+    }
+
+    #[test]
+    fn newton_fractal() {
+        let mut state = state();
+        state.root_picker_mut().set_scale(40.0);
+        state.root_picker_mut().set_polynomial(Polynomial::from_real(&[-1.0, 0.0, 0.0, 1.0])).unwrap();
+        state.show_newton_fractal(true);
+        assert_snapshot("newton_fractal", &state.capture_scene(SceneName::RootPicker), TOLERANCE);
+    }
+
+    #[test]
+    fn phase_portrait_arrows_and_trajectories() {
+        let mut state = state();
+        state.phase_portrait_mut().set_scale(20.0);
+        // a stable spiral
+        state.phase_portrait_mut().set_field(|x, y| [-0.3 * x - y, x - 0.3 * y]);
+        state.phase_portrait_mut().add_trajectory([2.5, 0.0]);
+        assert_snapshot("phase_portrait", &state.capture_scene(SceneName::PhasePortrait), TOLERANCE);
+    }
+
+    #[test]
+    fn simulation_draws_stepped_systems() {
+        let mut state = state();
+        // drifts right at 60 pixels per second, for half a second
+        let drift = |_: f64, _: &State| Vector::new(vec![60.0, 0.0]);
+        let draw = |y: &State| vec![
+            EntityBuilder::from_shape(ShapeKind::Circle(10.0), vec![Vertex::new(y[0] as f32, y[1] as f32, 0.0, RED)]).unwrap().build()
+        ];
+        state.simulation_mut().add(OdeSystem::new(drift, Vector::new(vec![0.0, 0.0]), Integrator::RungeKutta4, draw));
+        state.simulation_mut().set_timestep(0.125);
+        state.simulation_mut().advance(0.5);
+        assert_eq!(state.simulation().time(), 0.5);
+        assert_snapshot("simulation", &state.capture_scene(SceneName::Simulation), TOLERANCE);
+    }
+
+    #[test]
+    fn particles_draw_instanced() {
+        let mut state = state();
+        let mut system = ParticleSystem::new(6.0);
+        system.spawn(Particle::new([-40.0, 20.0], [0.0, 0.0], RED, f32::INFINITY));
+        system.spawn(Particle::new([0.0, 0.0], [0.0, 0.0], WHITE, f32::INFINITY));
+        // half its life gone, so half the size
+        let mut aging = Particle::new([40.0, -20.0], [0.0, 0.0], BLUE, 2.0);
+        aging.age = 1.0;
+        system.spawn(aging);
+        state.simulation_mut().add(system);
+        assert_snapshot("particles", &state.capture_scene(SceneName::Simulation), TOLERANCE);
+    }
+
+    #[test]
+    fn rigid_bodies_follow_their_transforms() {
+        let mut state = state();
+        let mut world = World::new([0.0, 0.0]);
+        world.add(Body::from_shape(ShapeKind::Rectangle, vec![
+            Vertex::new(-60.0, 10.0, 0.0, RED),
+            Vertex::new(-60.0, -10.0, 0.0, RED),
+            Vertex::new(-20.0, -10.0, 0.0, WHITE),
+            Vertex::new(-20.0, 10.0, 0.0, WHITE)
+        ], 1.0).unwrap().rotated(std::f32::consts::FRAC_PI_4));
+        world.add(Body::from_shape(ShapeKind::Circle(20.0), vec![Vertex::new(30.0, 0.0, 0.0, BLUE)], 1.0).unwrap().moving([40.0, 0.0], 1.0));
+        state.simulation_mut().add(world);
+        state.simulation_mut().set_timestep(0.125);
+        state.simulation_mut().advance(0.5);
+        assert_snapshot("rigid_bodies", &state.capture_scene(SceneName::Simulation), TOLERANCE);
+    }
+
+    #[test]
+    fn text_wraps_and_aligns() {
+        let mut state = state();
+        state.add_shape(&SceneName::Simulation, &ShapeKind::Circle(30.0), vec![Vertex::new(0.0, 0.0, 0.0, BLUE)]);
+        state.add_text(&SceneName::Simulation, &Text::new("Marlin draws text", 18.0).max_width(100.0).align(Align::Center), [0.0, 50.0]);
+        state.add_text(&SceneName::Simulation, &Text::new("AVA", 14.0).color(RED), [-75.0, -40.0]);
+        assert_snapshot("text", &state.capture_scene(SceneName::Simulation), TOLERANCE);
+    }
+
+    #[test]
+    fn overlapping_buttons() {
+        let mut state = state();
+        let raised = state.add_button(&SceneName::Home, &ShapeKind::Circle(30.0), vec![Vertex::new(-15.0, 0.0, 0.0, RED)], SceneName::Grapher);
+        state.button_mut(raised).set_layer(1);
+        state.add_button(&SceneName::Home, &ShapeKind::Triangle, vec![
+            Vertex::new(-10.0, -40.0, 0.0, BLUE),
+            Vertex::new(60.0, -40.0, 0.0, BLUE),
+            Vertex::new(-10.0, 40.0, 0.0, BLUE)
+        ], SceneName::Simulation);
+        assert_snapshot("overlapping_buttons", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn focused_slider() {
+        let mut state = state();
+        let slider = state.add_slider(Slider::new(SceneName::Home, [-60.0, -20.0], 120.0, 0.0, 10.0).step(1.0).initial(7.0).label("Radius"));
+        state.slider_mut(slider).set_focused(true);
+        assert_snapshot("slider", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn labelled_buttons_show_their_state() {
+        let mut state = state();
+        let rectangle = |left: f32, color| vec![
+            Vertex::new(left, 40.0, 0.0, color),
+            Vertex::new(left, 10.0, 0.0, color),
+            Vertex::new(left + 60.0, 10.0, 0.0, color),
+            Vertex::new(left + 60.0, 40.0, 0.0, color)
+        ];
+        let hovered = state.add_button(&SceneName::Home, &ShapeKind::Rectangle, rectangle(-70.0, BLUE), SceneName::Grapher);
+        state.button_mut(hovered).set_label(Some(Text::new("Go", 16.0)));
+        state.button_mut(hovered).set_tooltip(Some("Graph it"));
+        let disabled = state.add_button(&SceneName::Home, &ShapeKind::Rectangle, rectangle(10.0, BLUE), SceneName::Grapher);
+        state.button_mut(disabled).set_label(Some(Text::new("Off", 16.0)));
+        state.button_mut(disabled).set_enabled(false);
+        state.move_cursor([-40.0, 25.0]);
+        assert_snapshot("labelled_buttons", &state.capture_scene(SceneName::Home), TOLERANCE);
+    }
+
+    #[test]
+    fn gradient_background() {
+        let mut state = state();
+        state.set_background(&SceneName::Simulation, Background::VerticalGradient { top: BLUE, bottom: BLACK });
+        state.add_shape(&SceneName::Simulation, &ShapeKind::Circle(15.0), vec![Vertex::new(0.0, 0.0, 0.0, WHITE)]);
+        assert_snapshot("gradient_background", &state.capture_scene(SceneName::Simulation), TOLERANCE);
+    }
+
+    #[test]
+    fn buttons_draw_over_shapes_in_their_scene() {
+        let mut state = state();
+        state.add_shape(&SceneName::RootPicker, &ShapeKind::Circle(50.0), vec![Vertex::new(0.0, 0.0, 0.0, RED)]);
+        state.add_button(&SceneName::RootPicker, &ShapeKind::Rectangle, vec![
+            Vertex::new(-20.0, 5.0, 0.0, WHITE),
+            Vertex::new(-20.0, -5.0, 0.0, WHITE),
+            Vertex::new(20.0, -5.0, 0.0, WHITE),
+            Vertex::new(20.0, 5.0, 0.0, WHITE)
+        ], SceneName::Home);
+        state.add_button(&SceneName::Home, &ShapeKind::Triangle, vec![
+            Vertex::new(-20.0, -20.0, 0.0, BLUE),
+            Vertex::new(20.0, -20.0, 0.0, BLUE),
+            Vertex::new(0.0, 20.0, 0.0, BLUE)
+        ], SceneName::RootPicker);
+        assert_snapshot("root_picker_with_button", &state.capture_scene(SceneName::RootPicker), TOLERANCE);
+    }
+
+    #[test]
+    fn grapher_amplitude_slider() {
+        let mut state = MasterWindowState::software(crate::EXPORT_WIDTH, crate::EXPORT_HEIGHT);
+        crate::populate(&mut state);
+        state.set_scene(SceneName::Grapher);
+        // the right end of the track, doubling the sine wave and its derivative
+        state.click([100.0, -260.0]);
+        assert_snapshot("grapher_amplitude", &state.capture_scene(SceneName::Grapher), TOLERANCE);
+    }
+
+    // the goldens are all drawn in software, so check wgpu draws the same
+    // frames wherever there is an adapter to run it on
+    #[test]
+    fn renderers_agree() {
+        let Some(mut gpu) = tokio::runtime::Runtime::new().unwrap().block_on(MasterWindowState::offscreen(WIDTH, HEIGHT)) else {
+            eprintln!("no wgpu adapter, skipping");
+            return;
+        };
+        let mut software = state();
+        for state in [&mut gpu, &mut software] {
+            state.set_background(&SceneName::Simulation, Background::VerticalGradient { top: BLUE, bottom: BLACK });
+            state.add_shape(&SceneName::Simulation, &ShapeKind::Triangle, vec![
+                Vertex::new(-70.0, -50.0, 0.0, RED),
+                Vertex::new(-10.0, -50.0, 0.0, BLUE),
+                Vertex::new(-40.0, 0.0, 0.0, WHITE)
+            ]);
+            state.add_shape(&SceneName::Simulation, &ShapeKind::Circle(20.0), vec![Vertex::new(40.0, -20.0, 0.0, RED)]);
+            state.add_line(&SceneName::Simulation, vec![
+                Vertex::new(-60.0, 20.0, 0.0, WHITE),
+                Vertex::new(0.0, 40.0, 0.0, WHITE),
+                Vertex::new(60.0, 20.0, 0.0, WHITE)
+            ], &Stroke::new(4.0).join(LineJoin::Round));
+            state.add_text(&SceneName::Simulation, &Text::new("Marlin", 18.0), [-30.0, 55.0]);
+            // textured, but without a texture to sample, so neither renderer draws it
+            let mut untextured = EntityBuilder::from_shape(ShapeKind::Rectangle, vec![
+                Vertex::new(20.0, 50.0, 0.0, WHITE),
+                Vertex::new(20.0, 10.0, 0.0, WHITE),
+                Vertex::new(70.0, 10.0, 0.0, WHITE),
+                Vertex::new(70.0, 50.0, 0.0, WHITE)
+            ]).unwrap().textured(TextureId(0)).build();
+            untextured.texture = None;
+            state.add_entity(&SceneName::Simulation, untextured);
+            let mut particles = ParticleSystem::new(5.0);
+            for x in [-60.0, 60.0] {
+                particles.spawn(Particle::new([x, -45.0], [0.0, 0.0], WHITE, f32::INFINITY));
+            }
+            state.simulation_mut().add(particles);
+        }
+
+        let comparison = compare(&gpu.capture_scene(SceneName::Simulation), &software.capture_scene(SceneName::Simulation), TOLERANCE);
+
+        // rasterizers may round coverage differently along edges
+        assert!(comparison.mismatched_pixels <= (WIDTH * HEIGHT / 100) as usize, "{} pixels differ", comparison.mismatched_pixels);
+    }
+
+}
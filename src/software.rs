@@ -0,0 +1,284 @@
+use std::collections::HashMap;
+
+use crate::colors::Background;
+use crate::entities::Vertex;
+use crate::image::Image;
+use crate::pipeline::{Blend, Fragment, PipelineKey, ShaderId, TextureId, Topology};
+use crate::renderer::{Frame, RenderError, Renderer};
+use crate::transform::Transform;
+
+// A pure-CPU stand-in for the wgpu pipeline: the same triangle topologies,
+// counter-clockwise front faces with back faces culled and per-vertex colors
+// interpolated across each triangle. Custom shaders cannot run here, so entities
+// using one are shaded by the fallback registered for it, or the way shader.wgsl
+// would shade them when there is none. Textures are sampled the way
+// textured.wgsl samples them.
+pub struct SoftwareRenderer {
+    framebuffer: Image,
+    registered_shaders: usize,
+    fallbacks: HashMap<ShaderId, Fragment>,
+    textures: Vec<Image>,
+    // the parameters of the frame being drawn
+    parameters: Vec<[f32; 4]>
+}
+
+// how the covered pixels of a triangle get their color
+#[derive(Clone, Copy)]
+struct Shading {
+    blend: Blend,
+    fragment: Option<Fragment>,
+    texture: Option<TextureId>
+}
+
+impl Default for Shading {
+    fn default() -> Self {
+        Self { blend: Blend::Replace, fragment: None, texture: None }
+    }
+}
+
+impl SoftwareRenderer {
+
+    pub fn new(width: u32, height: u32) -> SoftwareRenderer {
+        Self {
+            framebuffer: Image::new(width, height),
+            // shader.wgsl, instanced.wgsl and textured.wgsl
+            registered_shaders: 3,
+            fallbacks: HashMap::new(),
+            textures: vec![],
+            parameters: vec![]
+        }
+    }
+
+    pub fn framebuffer(&self) -> &Image {
+        &self.framebuffer
+    }
+
+    fn clear(&mut self, background: &Background, view_projection: &Transform) {
+        let rgba = background.clear_color().as_rgba8();
+        for y in 0..self.framebuffer.height() {
+            for x in 0..self.framebuffer.width() {
+                self.framebuffer.set_pixel(x, y, rgba);
+            }
+        }
+        for triangle in background.vertices(view_projection).chunks_exact(3) {
+            self.rasterize(&triangle[0], &triangle[1], &triangle[2], view_projection, Shading::default());
+        }
+    }
+
+    fn draw(&mut self, frame: &Frame) {
+        self.parameters = frame.parameters.to_vec();
+        self.clear(&frame.background, &frame.screen_projection);
+        for (entities, view_projection) in frame.layers() {
+            for entity in entities {
+                let transformed: Vec<Vertex>;
+                let vertices = if entity.transform == Transform::IDENTITY {
+                    &entity.vertices
+                } else {
+                    transformed = entity.vertices.iter().map(|vertex| vertex.transformed(&entity.transform)).collect();
+                    &transformed
+                };
+                let texture = entity.texture.filter(|_| entity.pipeline.textured);
+                // nothing to sample, so skipped just as the GPU skips it
+                if entity.pipeline.textured && texture.is_none() {
+                    continue;
+                }
+                match &entity.instances {
+                    None => self.draw_vertices(vertices, entity.pipeline, texture, view_projection),
+                    Some(instances) => {
+                        for instance in instances {
+                            let placed: Vec<Vertex> = vertices.iter().map(|vertex| instance.place(vertex)).collect();
+                            self.draw_vertices(&placed, entity.pipeline, texture, view_projection);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw_vertices(&mut self, vertices: &[Vertex], pipeline: PipelineKey, texture: Option<TextureId>, view_projection: &Transform) {
+        let shading = Shading {
+            blend: pipeline.blend,
+            fragment: self.fallbacks.get(&pipeline.shader).copied(),
+            texture
+        };
+        match pipeline.topology {
+            Topology::TriangleList => {
+                for triangle in vertices.chunks_exact(3) {
+                    self.rasterize(&triangle[0], &triangle[1], &triangle[2], view_projection, shading);
+                }
+            },
+            Topology::TriangleStrip => {
+                // every other triangle of a strip is wound the opposite way
+                for (i, triangle) in vertices.windows(3).enumerate() {
+                    if i % 2 == 0 {
+                        self.rasterize(&triangle[0], &triangle[1], &triangle[2], view_projection, shading);
+                    } else {
+                        self.rasterize(&triangle[1], &triangle[0], &triangle[2], view_projection, shading);
+                    }
+                }
+            }
+        }
+    }
+
+    // projects a world-space vertex onto the framebuffer, keeping y pointing up
+    fn to_pixel_space(&self, vertex: &Vertex, view_projection: &Transform) -> [f32; 2] {
+        let [x, y] = view_projection.apply([vertex.position[0], vertex.position[1]]);
+        [
+            (x + 1.0) / 2.0 * self.framebuffer.width() as f32,
+            (y + 1.0) / 2.0 * self.framebuffer.height() as f32
+        ]
+    }
+
+    fn edge(from: [f32; 2], to: [f32; 2], point: [f32; 2]) -> f32 {
+        (to[0] - from[0]) * (point[1] - from[1]) - (to[1] - from[1]) * (point[0] - from[0])
+    }
+
+    // top-left fill rule: pixels lying exactly on a shared edge belong to only one triangle
+    fn owns_edge(from: [f32; 2], to: [f32; 2]) -> bool {
+        let dy = to[1] - from[1];
+        let dx = to[0] - from[0];
+        dy < 0.0 || (dy == 0.0 && dx < 0.0)
+    }
+
+    fn blend(source: [u8; 4], destination: [u8; 4], blend: Blend) -> [u8; 4] {
+        match blend {
+            Blend::Replace => source,
+            Blend::Alpha => {
+                // matches wgpu::BlendState::ALPHA_BLENDING
+                let alpha = source[3] as f32 / 255.0;
+                let mut blended = [0; 4];
+                for (i, channel) in blended.iter_mut().enumerate() {
+                    let source_factor = if i == 3 { 1.0 } else { alpha };
+                    let mixed = source[i] as f32 * source_factor + destination[i] as f32 * (1.0 - alpha);
+                    *channel = mixed.round().clamp(0.0, 255.0) as u8;
+                }
+                blended
+            }
+        }
+    }
+
+    fn rasterize(&mut self, a: &Vertex, b: &Vertex, c: &Vertex, view_projection: &Transform, shading: Shading) {
+
+        let corners = [
+            self.to_pixel_space(a, view_projection),
+            self.to_pixel_space(b, view_projection),
+            self.to_pixel_space(c, view_projection)
+        ];
+
+        let area = Self::edge(corners[0], corners[1], corners[2]);
+        // clockwise => back face, zero => degenerate
+        if area <= 0.0 {
+            return;
+        }
+
+        let width = self.framebuffer.width();
+        let height = self.framebuffer.height();
+
+        let min_x = corners.iter().map(|p| p[0]).fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+        let max_x = corners.iter().map(|p| p[0]).fold(f32::NEG_INFINITY, f32::max).ceil().min(width as f32) as u32;
+        let min_y = corners.iter().map(|p| p[1]).fold(f32::INFINITY, f32::min).floor().max(0.0) as u32;
+        let max_y = corners.iter().map(|p| p[1]).fold(f32::NEG_INFINITY, f32::max).ceil().min(height as f32) as u32;
+
+        let owned = [
+            Self::owns_edge(corners[1], corners[2]),
+            Self::owns_edge(corners[2], corners[0]),
+            Self::owns_edge(corners[0], corners[1])
+        ];
+
+        for column in min_x..max_x {
+            for row in min_y..max_y {
+
+                let center = [column as f32 + 0.5, row as f32 + 0.5];
+
+                let weights = [
+                    Self::edge(corners[1], corners[2], center),
+                    Self::edge(corners[2], corners[0], center),
+                    Self::edge(corners[0], corners[1], center)
+                ];
+
+                let covered = weights.iter().zip(owned).all(|(&w, owned)| w > 0.0 || (w == 0.0 && owned));
+                if !covered {
+                    continue;
+                }
+
+                let [wa, wb, wc] = weights.map(|w| w / area);
+
+                // the wgpu pipeline clips anything outside the 0..1 depth range
+                let depth = wa * a.position[2] + wb * b.position[2] + wc * c.position[2];
+                if !(0.0..=1.0).contains(&depth) {
+                    continue;
+                }
+
+                let interpolate = |attribute: fn(&Vertex) -> [f32; 3]| {
+                    let [a, b, c] = [a, b, c].map(attribute);
+                    [0, 1, 2].map(|i| wa * a[i] + wb * b[i] + wc * c[i])
+                };
+                let mut color = interpolate(|v| v.color);
+                let mut alpha = 1.0;
+                if let Some(fragment) = shading.fragment {
+                    let [x, y, _] = interpolate(|v| v.position);
+                    color = fragment([x, y], color, &self.parameters);
+                }
+                if let Some(texture) = shading.texture {
+                    let [u, v, _] = interpolate(|v| [v.texel[0], v.texel[1], 0.0]);
+                    let sampled = self.textures[texture.0].sample([u, v]);
+                    color = [0, 1, 2].map(|i| color[i] * sampled[i]);
+                    alpha = sampled[3];
+                }
+
+                let mut rgba = [255; 4];
+                for (value, channel) in rgba.iter_mut().zip(color.into_iter().chain([alpha])) {
+                    *value = (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+
+                // framebuffer rows run top to bottom
+                let y = height - 1 - row;
+                let blended = Self::blend(rgba, self.framebuffer.pixel(column, y), shading.blend);
+                self.framebuffer.set_pixel(column, y, blended);
+
+            }
+        }
+
+    }
+
+}
+
+impl Renderer for SoftwareRenderer {
+
+    fn dimensions(&self) -> (u32, u32) {
+        (self.framebuffer.width(), self.framebuffer.height())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        self.framebuffer = Image::new(width, height);
+    }
+
+    fn register_shader(&mut self, _label: &str, _source: &str) -> ShaderId {
+        self.registered_shaders += 1;
+        ShaderId(self.registered_shaders - 1)
+    }
+
+    fn register_fallback(&mut self, shader: ShaderId, fragment: Fragment) {
+        self.fallbacks.insert(shader, fragment);
+    }
+
+    fn register_texture(&mut self, image: &Image) -> TextureId {
+        self.textures.push(image.clone());
+        TextureId(self.textures.len() - 1)
+    }
+
+    fn update_texture(&mut self, texture: TextureId, image: &Image) {
+        self.textures[texture.0] = image.clone();
+    }
+
+    fn render(&mut self, frame: &Frame) -> Result<(), RenderError> {
+        self.draw(frame);
+        Ok(())
+    }
+
+    fn capture(&mut self, frame: &Frame) -> Image {
+        self.draw(frame);
+        self.framebuffer.clone()
+    }
+
+}
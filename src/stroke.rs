@@ -0,0 +1,366 @@
+use crate::entities::{ShapeError, Vertex};
+
+// miters longer than this many half-widths are beveled instead, as in SVG
+const MITER_LIMIT: f32 = 4.0;
+// how finely round joins and caps are divided
+const ROUND_STEPS_PER_RADIAN: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineJoin {
+    Miter,
+    Round,
+    Bevel
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineCap {
+    Butt,
+    Round,
+    Square
+}
+
+// How a polyline is turned into triangles. The width and dash lengths are
+// measured in logical pixels on screen, whatever the zoom it is tessellated
+// for. A dash pattern alternates drawn and skipped lengths along the line,
+// starting `dash_offset` into it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Stroke {
+    width: f32,
+    join: LineJoin,
+    cap: LineCap,
+    dash: Vec<f32>,
+    dash_offset: f32
+}
+
+impl Stroke {
+
+    pub fn new(width: f32) -> Stroke {
+        Self {
+            width,
+            join: LineJoin::Miter,
+            cap: LineCap::Butt,
+            dash: vec![],
+            dash_offset: 0.0
+        }
+    }
+
+    pub fn join(mut self, join: LineJoin) -> Stroke {
+        self.join = join;
+        self
+    }
+
+    pub fn cap(mut self, cap: LineCap) -> Stroke {
+        self.cap = cap;
+        self
+    }
+
+    pub fn dash(mut self, pattern: Vec<f32>, offset: f32) -> Stroke {
+        self.dash = pattern;
+        self.dash_offset = offset;
+        self
+    }
+
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    // tessellates the polyline through `points`, in world units, into a
+    // counter-clockwise TriangleList to be seen at `zoom` pixels per world
+    // unit; every vertex keeps the color of the point it came from
+    pub fn tessellate(&self, points: &[Vertex], zoom: f32) -> Result<Vec<Vertex>, ShapeError> {
+        if zoom <= 0.0 {
+            return Err(ShapeError::Degenerate);
+        }
+        Self {
+            width: self.width / zoom,
+            join: self.join,
+            cap: self.cap,
+            dash: self.dash.iter().map(|length| length / zoom).collect(),
+            dash_offset: self.dash_offset / zoom
+        }.tessellate_in_world(points)
+    }
+
+    fn tessellate_in_world(&self, points: &[Vertex]) -> Result<Vec<Vertex>, ShapeError> {
+
+        if points.len() < 2 {
+            return Err(ShapeError::TooFewPoints(points.len()));
+        }
+        let dash_length: f32 = self.dash.iter().sum();
+        if self.width <= 0.0 || self.dash.iter().any(|&length| length < 0.0) || (!self.dash.is_empty() && dash_length <= 0.0) {
+            return Err(ShapeError::Degenerate);
+        }
+
+        let points = distinct_points(points);
+        if points.len() < 2 {
+            return Err(ShapeError::Degenerate);
+        }
+
+        let pieces = if self.dash.is_empty() {
+            vec![points]
+        } else {
+            self.dashes(&points, dash_length)
+        };
+
+        let mut triangles = vec![];
+        for piece in pieces {
+            let piece = distinct_points(&piece);
+            if piece.len() >= 2 {
+                self.tessellate_piece(&piece, &mut triangles);
+            }
+        }
+        Ok(triangles)
+    }
+
+    // splits the line into the runs the dash pattern draws
+    fn dashes(&self, points: &[Vertex], dash_length: f32) -> Vec<Vec<Vertex>> {
+
+        let mut index = 0;
+        let mut on = true;
+        let mut remaining = self.dash_offset.rem_euclid(dash_length);
+        while remaining >= self.dash[index] {
+            remaining -= self.dash[index];
+            index = (index + 1) % self.dash.len();
+            on = !on;
+        }
+        remaining = self.dash[index] - remaining;
+
+        let mut pieces = vec![];
+        let mut current = if on { vec![points[0]] } else { vec![] };
+
+        for pair in points.windows(2) {
+            let length = distance(&pair[0], &pair[1]);
+            let mut travelled = 0.0;
+            while length - travelled > remaining {
+                travelled += remaining;
+                current.push(lerp(&pair[0], &pair[1], travelled / length));
+                if on {
+                    pieces.push(std::mem::take(&mut current));
+                }
+                on = !on;
+                index = (index + 1) % self.dash.len();
+                remaining = self.dash[index];
+            }
+            remaining -= length - travelled;
+            if on {
+                current.push(pair[1]);
+            }
+        }
+
+        if on {
+            pieces.push(current);
+        }
+        pieces
+    }
+
+    fn tessellate_piece(&self, points: &[Vertex], out: &mut Vec<Vertex>) {
+
+        let half = self.width / 2.0;
+        let mut points = points.to_vec();
+        let last = points.len() - 1;
+        let start_direction = direction(&points[0], &points[1]);
+        let end_direction = direction(&points[last - 1], &points[last]);
+
+        if self.cap == LineCap::Square {
+            points[0] = shift(&points[0], scaled(start_direction, -half));
+            points[last] = shift(&points[last], scaled(end_direction, half));
+        }
+
+        for pair in points.windows(2) {
+            let normal = scaled(left_normal(direction(&pair[0], &pair[1])), half);
+            let (a, b) = (shift(&pair[0], normal), shift(&pair[0], scaled(normal, -1.0)));
+            let (c, d) = (shift(&pair[1], normal), shift(&pair[1], scaled(normal, -1.0)));
+            push_triangle(out, b, d, c);
+            push_triangle(out, c, a, b);
+        }
+
+        for corner in points.windows(3) {
+            self.join_segments(&corner[0], &corner[1], &corner[2], half, out);
+        }
+
+        if self.cap == LineCap::Round {
+            fan(out, &points[0], left_normal(start_direction), std::f32::consts::PI, half);
+            fan(out, &points[last], scaled(left_normal(end_direction), -1.0), std::f32::consts::PI, half);
+        }
+    }
+
+    // fills the gap on the outside of the corner at `point`; the inside is
+    // already covered where the two segments overlap
+    fn join_segments(&self, before: &Vertex, point: &Vertex, after: &Vertex, half: f32, out: &mut Vec<Vertex>) {
+
+        let (incoming, outgoing) = (direction(before, point), direction(point, after));
+        let turn = cross(incoming, outgoing);
+        if turn.abs() <= f32::EPSILON && dot(incoming, outgoing) > 0.0 {
+            return;
+        }
+
+        // the outside of a left turn is on the right
+        let side = if turn >= 0.0 { -1.0 } else { 1.0 };
+        let outer_in = scaled(left_normal(incoming), side);
+        let outer_out = scaled(left_normal(outgoing), side);
+        let a = shift(point, scaled(outer_in, half));
+        let b = shift(point, scaled(outer_out, half));
+
+        match self.join {
+            LineJoin::Bevel => push_triangle(out, *point, a, b),
+            LineJoin::Round => {
+                let angle = cross(outer_in, outer_out).atan2(dot(outer_in, outer_out));
+                fan(out, point, outer_in, angle, half);
+            },
+            LineJoin::Miter => {
+                let bisector = [outer_in[0] + outer_out[0], outer_in[1] + outer_out[1]];
+                let bisector_length = length(bisector);
+                let miter = if bisector_length > f32::EPSILON {
+                    let bisector = scaled(bisector, 1.0 / bisector_length);
+                    Some(half / dot(bisector, outer_in)).filter(|&miter| miter <= MITER_LIMIT * half).map(|miter| scaled(bisector, miter))
+                } else {
+                    None
+                };
+                match miter {
+                    Some(miter) => {
+                        let tip = shift(point, miter);
+                        push_triangle(out, *point, a, tip);
+                        push_triangle(out, *point, tip, b);
+                    },
+                    None => push_triangle(out, *point, a, b)
+                }
+            }
+        }
+    }
+
+}
+
+// a fan of triangles around `center`, sweeping `angle` radians counter-clockwise from `from`
+fn fan(out: &mut Vec<Vertex>, center: &Vertex, from: [f32; 2], angle: f32, radius: f32) {
+    let steps = (angle.abs() * ROUND_STEPS_PER_RADIAN).ceil().max(1.0) as usize;
+    let mut previous = shift(center, scaled(from, radius));
+    for step in 1..=steps {
+        let (sin, cos) = (angle * step as f32 / steps as f32).sin_cos();
+        let rotated = [from[0] * cos - from[1] * sin, from[0] * sin + from[1] * cos];
+        let next = shift(center, scaled(rotated, radius));
+        push_triangle(out, *center, previous, next);
+        previous = next;
+    }
+}
+
+// appends a, b and c in whichever order faces the camera
+fn push_triangle(out: &mut Vec<Vertex>, a: Vertex, b: Vertex, c: Vertex) {
+    let winding = (b.position[0] - a.position[0]) * (c.position[1] - a.position[1])
+        - (b.position[1] - a.position[1]) * (c.position[0] - a.position[0]);
+    if winding == 0.0 {
+        return;
+    }
+    if winding > 0.0 {
+        out.extend([a, b, c]);
+    } else {
+        out.extend([a, c, b]);
+    }
+}
+
+fn distinct_points(points: &[Vertex]) -> Vec<Vertex> {
+    let mut distinct: Vec<Vertex> = Vec::with_capacity(points.len());
+    for point in points {
+        if !distinct.last().is_some_and(|last| distance(last, point) == 0.0) {
+            distinct.push(*point);
+        }
+    }
+    distinct
+}
+
+fn lerp(a: &Vertex, b: &Vertex, t: f32) -> Vertex {
+    let mut point = *a;
+    for i in 0..3 {
+        point.position[i] += (b.position[i] - a.position[i]) * t;
+        point.color[i] += (b.color[i] - a.color[i]) * t;
+    }
+    point
+}
+
+fn shift(vertex: &Vertex, by: [f32; 2]) -> Vertex {
+    let mut shifted = *vertex;
+    shifted.position[0] += by[0];
+    shifted.position[1] += by[1];
+    shifted
+}
+
+fn distance(a: &Vertex, b: &Vertex) -> f32 {
+    length([b.position[0] - a.position[0], b.position[1] - a.position[1]])
+}
+
+fn direction(from: &Vertex, to: &Vertex) -> [f32; 2] {
+    let delta = [to.position[0] - from.position[0], to.position[1] - from.position[1]];
+    scaled(delta, 1.0 / length(delta))
+}
+
+fn left_normal(direction: [f32; 2]) -> [f32; 2] {
+    [-direction[1], direction[0]]
+}
+
+fn scaled(v: [f32; 2], factor: f32) -> [f32; 2] {
+    [v[0] * factor, v[1] * factor]
+}
+
+fn length(v: [f32; 2]) -> f32 {
+    v[0].hypot(v[1])
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn cross(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[1] - a[1] * b[0]
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    use crate::colors::WHITE;
+
+    fn line(points: &[[f32; 2]]) -> Vec<Vertex> {
+        points.iter().map(|p| Vertex::new(p[0], p[1], 0.0, WHITE)).collect()
+    }
+
+    fn area(triangles: &[Vertex]) -> f32 {
+        triangles.chunks_exact(3).map(|t| {
+            let (a, b, c) = (t[0].position, t[1].position, t[2].position);
+            ((b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0])) / 2.0
+        }).sum()
+    }
+
+    #[test]
+    fn caps_extend_a_straight_line() {
+        let points = line(&[[0.0, 0.0], [10.0, 0.0]]);
+        let butt = Stroke::new(2.0).tessellate(&points, 1.0).unwrap();
+        let square = Stroke::new(2.0).cap(LineCap::Square).tessellate(&points, 1.0).unwrap();
+        assert_eq!(area(&butt), 20.0);
+        assert_eq!(area(&square), 24.0);
+        assert!(butt.chunks_exact(3).chain(square.chunks_exact(3)).all(|t| area(t) > 0.0));
+    }
+
+    #[test]
+    fn dashes_split_the_line() {
+        // 12 units of line drawn 3 on, 1 off from the start of the pattern
+        let points = line(&[[0.0, 0.0], [6.0, 0.0], [12.0, 0.0]]);
+        let dashed = Stroke::new(2.0).dash(vec![3.0, 1.0], 0.0).tessellate(&points, 1.0).unwrap();
+        assert!((area(&dashed) - 2.0 * 9.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn miter_join_fills_the_outer_corner() {
+        let corner = line(&[[0.0, 0.0], [10.0, 0.0], [10.0, 10.0]]);
+        let bevel = Stroke::new(2.0).join(LineJoin::Bevel).tessellate(&corner, 1.0).unwrap();
+        let miter = Stroke::new(2.0).join(LineJoin::Miter).tessellate(&corner, 1.0).unwrap();
+        assert!((area(&miter) - area(&bevel) - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn widths_and_dashes_stay_in_pixels_when_zoomed() {
+        let points = line(&[[0.0, 0.0], [6.0, 0.0], [12.0, 0.0]]);
+        // 1 world unit wide, drawn 3 on, 1 off
+        let zoomed = Stroke::new(2.0).dash(vec![6.0, 2.0], 0.0).tessellate(&points, 2.0).unwrap();
+        assert!((area(&zoomed) - 9.0).abs() < 1e-4);
+    }
+
+}
@@ -0,0 +1,353 @@
+use crate::expression::{BinaryOperator, Constant, Expression, Function};
+
+// simplification rewrites bottom-up until nothing changes, or gives up here
+const MAX_SIMPLIFY_PASSES: usize = 32;
+
+fn number(value: f64) -> Expression {
+    Expression::Number(value)
+}
+
+fn negate(operand: Expression) -> Expression {
+    Expression::Negate(Box::new(operand))
+}
+
+fn binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+    Expression::Binary(operator, Box::new(left), Box::new(right))
+}
+
+fn add(left: Expression, right: Expression) -> Expression {
+    binary(BinaryOperator::Add, left, right)
+}
+
+fn subtract(left: Expression, right: Expression) -> Expression {
+    binary(BinaryOperator::Subtract, left, right)
+}
+
+fn multiply(left: Expression, right: Expression) -> Expression {
+    binary(BinaryOperator::Multiply, left, right)
+}
+
+fn divide(left: Expression, right: Expression) -> Expression {
+    binary(BinaryOperator::Divide, left, right)
+}
+
+fn power(base: Expression, exponent: Expression) -> Expression {
+    binary(BinaryOperator::Power, base, exponent)
+}
+
+fn call(function: Function, argument: Expression) -> Expression {
+    Expression::Call(function, Box::new(argument))
+}
+
+impl Expression {
+
+    pub fn depends_on(&self, variable: &str) -> bool {
+        match self {
+            Self::Number(_) | Self::Constant(_) => false,
+            Self::Variable(name) => name == variable,
+            Self::Negate(operand) | Self::Call(_, operand) => operand.depends_on(variable),
+            Self::Binary(_, left, right) => left.depends_on(variable) || right.depends_on(variable)
+        }
+    }
+
+    // the simplified derivative with respect to `variable`
+    pub fn derivative(&self, variable: &str) -> Expression {
+        self.differentiate(variable).simplified()
+    }
+
+    fn differentiate(&self, variable: &str) -> Expression {
+
+        if !self.depends_on(variable) {
+            return number(0.0);
+        }
+
+        match self {
+            Self::Number(_) | Self::Constant(_) => number(0.0),
+            Self::Variable(_) => number(1.0),
+            Self::Negate(operand) => negate(operand.differentiate(variable)),
+            Self::Binary(operator, left, right) => {
+                let (u, v) = (left.as_ref().clone(), right.as_ref().clone());
+                let (du, dv) = (left.differentiate(variable), right.differentiate(variable));
+                match operator {
+                    BinaryOperator::Add => add(du, dv),
+                    BinaryOperator::Subtract => subtract(du, dv),
+                    BinaryOperator::Multiply => add(multiply(du, v), multiply(u, dv)),
+                    BinaryOperator::Divide => divide(
+                        subtract(multiply(du, v.clone()), multiply(u, dv)),
+                        power(v, number(2.0))
+                    ),
+                    // power rule: n u^(n - 1) u'
+                    BinaryOperator::Power if !right.depends_on(variable) => multiply(
+                        multiply(v.clone(), power(u, subtract(v, number(1.0)))),
+                        du
+                    ),
+                    // exponential rule: a^v ln(a) v'
+                    BinaryOperator::Power if !left.depends_on(variable) => multiply(
+                        multiply(self.clone(), call(Function::Ln, u)),
+                        dv
+                    ),
+                    // u^v (v' ln(u) + v u' / u)
+                    BinaryOperator::Power => multiply(
+                        self.clone(),
+                        add(
+                            multiply(dv, call(Function::Ln, u.clone())),
+                            divide(multiply(v, du), u)
+                        )
+                    )
+                }
+            },
+            Self::Call(function, argument) => {
+                let u = argument.as_ref().clone();
+                let du = argument.differentiate(variable);
+                let outer = match function {
+                    Function::Sin => call(Function::Cos, u),
+                    Function::Cos => negate(call(Function::Sin, u)),
+                    Function::Tan => divide(number(1.0), power(call(Function::Cos, u), number(2.0))),
+                    Function::Asin => divide(number(1.0), call(Function::Sqrt, subtract(number(1.0), power(u, number(2.0))))),
+                    Function::Acos => negate(divide(number(1.0), call(Function::Sqrt, subtract(number(1.0), power(u, number(2.0)))))),
+                    Function::Atan => divide(number(1.0), add(number(1.0), power(u, number(2.0)))),
+                    Function::Sinh => call(Function::Cosh, u),
+                    Function::Cosh => call(Function::Sinh, u),
+                    Function::Tanh => divide(number(1.0), power(call(Function::Cosh, u), number(2.0))),
+                    Function::Exp => call(Function::Exp, u),
+                    Function::Ln => divide(number(1.0), u),
+                    Function::Log => divide(number(1.0), multiply(u, call(Function::Ln, number(10.0)))),
+                    Function::Sqrt => divide(number(1.0), multiply(number(2.0), call(Function::Sqrt, u))),
+                    Function::Abs => divide(u.clone(), call(Function::Abs, u))
+                };
+                // chain rule
+                multiply(outer, du)
+            }
+        }
+    }
+
+    // an equivalent expression with identities applied, constants folded and
+    // products gathered as `coefficient * rest`
+    pub fn simplified(&self) -> Expression {
+        let mut current = self.clone();
+        for _ in 0..MAX_SIMPLIFY_PASSES {
+            let next = current.simplify_pass();
+            if next == current {
+                break;
+            }
+            current = next;
+        }
+        current
+    }
+
+    fn simplify_pass(&self) -> Expression {
+        match self {
+            Self::Negate(operand) => match operand.simplify_pass() {
+                Self::Number(value) => number(-value),
+                Self::Negate(inner) => *inner,
+                operand => negate(operand)
+            },
+            Self::Call(function, argument) => call(*function, argument.simplify_pass()),
+            Self::Binary(operator, left, right) => simplify_binary(*operator, left.simplify_pass(), right.simplify_pass()),
+            _ => self.clone()
+        }
+    }
+
+}
+
+fn is_number(expression: &Expression, value: f64) -> bool {
+    *expression == Expression::Number(value)
+}
+
+// splits u^n into (u, n), treating anything else as u^1
+fn base_and_exponent(expression: &Expression) -> (Expression, Expression) {
+    match expression {
+        Expression::Binary(BinaryOperator::Power, base, exponent) => (base.as_ref().clone(), exponent.as_ref().clone()),
+        other => (other.clone(), number(1.0))
+    }
+}
+
+fn simplify_binary(operator: BinaryOperator, left: Expression, right: Expression) -> Expression {
+
+    use Expression::{Negate, Number};
+
+    if let (Number(a), Number(b)) = (&left, &right) {
+        let folded = match operator {
+            BinaryOperator::Add => a + b,
+            BinaryOperator::Subtract => a - b,
+            BinaryOperator::Multiply => a * b,
+            BinaryOperator::Divide => a / b,
+            BinaryOperator::Power => a.powf(*b)
+        };
+        // leave 1/3 and friends alone rather than print them as 0.333...
+        let exact = match operator {
+            BinaryOperator::Divide | BinaryOperator::Power => folded.fract() == 0.0,
+            _ => true
+        };
+        if folded.is_finite() && exact {
+            return number(folded);
+        }
+    }
+
+    match operator {
+        BinaryOperator::Add => {
+            if is_number(&left, 0.0) {
+                return right;
+            }
+            if is_number(&right, 0.0) {
+                return left;
+            }
+            if left == right {
+                return multiply(number(2.0), left);
+            }
+            match (left, right) {
+                (left, Negate(right)) => subtract(left, *right),
+                (left, Number(value)) if value < 0.0 => subtract(left, number(-value)),
+                (Negate(left), right) => subtract(right, *left),
+                (left, right) => add(left, right)
+            }
+        },
+        BinaryOperator::Subtract => {
+            if is_number(&right, 0.0) {
+                return left;
+            }
+            if is_number(&left, 0.0) {
+                return negate(right);
+            }
+            if left == right {
+                return number(0.0);
+            }
+            match (left, right) {
+                (left, Negate(right)) => add(left, *right),
+                (left, Number(value)) if value < 0.0 => add(left, number(-value)),
+                (left, right) => subtract(left, right)
+            }
+        },
+        BinaryOperator::Multiply => {
+            if is_number(&left, 0.0) || is_number(&right, 0.0) {
+                return number(0.0);
+            }
+            if is_number(&left, 1.0) {
+                return right;
+            }
+            if is_number(&right, 1.0) {
+                return left;
+            }
+            if is_number(&left, -1.0) {
+                return negate(right);
+            }
+            match (left, right) {
+                (Negate(left), right) => negate(multiply(*left, right)),
+                (left, Negate(right)) => negate(multiply(left, *right)),
+                // coefficients move to the front
+                (left, Number(value)) => multiply(number(value), left),
+                // products lean left, so 2 * (x * y) prints as 2 * x * y
+                (left, Expression::Binary(BinaryOperator::Multiply, inner_left, inner_right)) => {
+                    multiply(multiply(left, *inner_left), *inner_right)
+                },
+                (Number(a), Expression::Binary(BinaryOperator::Divide, numerator, denominator)) if matches!(*numerator, Number(_)) => {
+                    divide(multiply(Number(a), *numerator), *denominator)
+                },
+                (left, right) => {
+                    // x^a * x^b => x^(a + b)
+                    let ((left_base, left_exponent), (right_base, right_exponent)) = (base_and_exponent(&left), base_and_exponent(&right));
+                    if left_base == right_base && !matches!(left_base, Number(_)) {
+                        return power(left_base, add(left_exponent, right_exponent));
+                    }
+                    multiply(left, right)
+                }
+            }
+        },
+        BinaryOperator::Divide => {
+            if is_number(&left, 0.0) && !is_number(&right, 0.0) {
+                return number(0.0);
+            }
+            if is_number(&right, 1.0) {
+                return left;
+            }
+            if left == right {
+                return number(1.0);
+            }
+            match (left, right) {
+                (Negate(left), right) => negate(divide(*left, right)),
+                (left, Negate(right)) => negate(divide(left, *right)),
+                (left, right) => divide(left, right)
+            }
+        },
+        BinaryOperator::Power => {
+            if is_number(&right, 0.0) {
+                return number(1.0);
+            }
+            if is_number(&right, 1.0) {
+                return left;
+            }
+            if is_number(&left, 1.0) {
+                return number(1.0);
+            }
+            match (left, right) {
+                // (x^a)^b => x^(a b) when both are plain numbers
+                (Expression::Binary(BinaryOperator::Power, base, inner), Number(outer)) if matches!(*inner, Number(_)) => {
+                    power(*base, multiply(*inner, Number(outer)))
+                },
+                (Expression::Constant(Constant::E), exponent) => call(Function::Exp, exponent),
+                (left, right) => power(left, right)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn derivative(source: &str) -> String {
+        Expression::parse(source).unwrap().derivative("x").to_string()
+    }
+
+    #[test]
+    fn derivatives_print_simplified() {
+        assert_eq!(derivative("x^3"), "3 * x^2");
+        assert_eq!(derivative("5x + 2"), "5");
+        assert_eq!(derivative("sin(x) * x^2 - 3"), "cos(x) * x^2 + 2 * sin(x) * x");
+        assert_eq!(derivative("1 / x"), "-1 / x^2");
+        assert_eq!(derivative("exp(2x)"), "2 * exp(2 * x)");
+        assert_eq!(derivative("y * x"), "y");
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let formulas = [
+            "x^3 - 2x", "sin(x) * x^2 - 3", "x / (1 + x^2)", "exp(-x^2)", "ln(x) * sqrt(x)",
+            "tan(x)", "atan(2x)", "asin(x / 2)", "acos(x / 3)", "cosh(x) - sinh(x) * tanh(x)",
+            "log(x)", "abs(x - 3)", "2^x", "x^x", "e^(3x)", "-cos(x) / x"
+        ];
+        for formula in formulas {
+            let expression = Expression::parse(formula).unwrap();
+            let function = expression.real_function("x").unwrap();
+            let derivative = expression.derivative("x").real_function("x").unwrap();
+            for x in [0.3, 0.7, 1.1, 1.6] {
+                let h = 1e-6;
+                let estimate = (function(x + h) - function(x - h)) / (2.0 * h);
+                assert!(
+                    (derivative(x) - estimate).abs() < 1e-5 * estimate.abs().max(1.0),
+                    "d/dx {} at {}: {} vs {}", formula, x, derivative(x), estimate
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn printing_reparses_to_the_same_tree() {
+        for formula in ["(-x)^2", "-x^2", "a - (b - c)", "2^3^2", "(2^3)^2", "a / (b * c)", "-(x + 1) * 3", "sin(x)^-1"] {
+            let expression = Expression::parse(formula).unwrap();
+            assert_eq!(Expression::parse(&expression.to_string()).unwrap(), expression, "{}", formula);
+        }
+    }
+
+    #[test]
+    fn simplification_applies_identities() {
+        let simplified = |source: &str| Expression::parse(source).unwrap().simplified().to_string();
+        assert_eq!(simplified("0 * x + 1 * y - 0"), "y");
+        assert_eq!(simplified("x * x * x"), "x^3");
+        assert_eq!(simplified("2 * (x * 3)"), "6 * x");
+        assert_eq!(simplified("x - x + --y"), "y");
+        assert_eq!(simplified("1 / 3"), "1 / 3");
+    }
+
+}
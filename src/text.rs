@@ -0,0 +1,431 @@
+use ab_glyph::{Font as _, FontArc, GlyphId, PxScale, ScaleFont};
+
+use std::collections::HashMap;
+
+use crate::colors::{Color, WHITE};
+use crate::entities::{Entity, EntityBuilder, Vertex};
+use crate::image::Image;
+use crate::pipeline::TextureId;
+
+const DEFAULT_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans.ttf");
+
+const ATLAS_WIDTH: u32 = 512;
+const INITIAL_ATLAS_HEIGHT: u32 = 128;
+// blank texels left around every glyph so filtering never bleeds into another
+const PADDING: u32 = 1;
+
+#[derive(Clone)]
+pub struct Font(FontArc);
+
+impl Font {
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Font, FontError> {
+        FontArc::try_from_vec(bytes).map(Font).map_err(|_| FontError::Invalid)
+    }
+
+    // in the font's units scaled to `size` pixels: ascent, descent below the
+    // baseline (negative) and the gap between lines
+    pub fn metrics(&self, size: f32) -> (f32, f32, f32) {
+        let scaled = self.0.as_scaled(PxScale::from(size));
+        (scaled.ascent(), scaled.descent(), scaled.line_gap())
+    }
+
+    pub fn line_height(&self, size: f32) -> f32 {
+        let (ascent, descent, line_gap) = self.metrics(size);
+        ascent - descent + line_gap
+    }
+
+    // lays `text` out in lines no wider than its maximum width, breaking at
+    // spaces; a word wider than that on its own overflows its line
+    pub fn layout(&self, text: &Text) -> Layout {
+
+        let scaled = self.0.as_scaled(PxScale::from(text.size));
+        let width = |line: &str| {
+            let mut previous: Option<GlyphId> = None;
+            line.chars().map(|c| {
+                let id = scaled.glyph_id(c);
+                let kerning = previous.map_or(0.0, |previous| scaled.kern(previous, id));
+                previous = Some(id);
+                kerning + scaled.h_advance(id)
+            }).sum::<f32>()
+        };
+
+        let mut lines: Vec<String> = vec![];
+        for paragraph in text.content.split('\n') {
+            let mut line = String::new();
+            for word in paragraph.split(' ') {
+                let candidate = if line.is_empty() { word.to_string() } else { format!("{} {}", line, word) };
+                let overflows = text.max_width.is_some_and(|max_width| width(&candidate) > max_width);
+                if overflows && !line.is_empty() {
+                    lines.push(std::mem::replace(&mut line, word.to_string()));
+                } else {
+                    line = candidate;
+                }
+            }
+            lines.push(line);
+        }
+
+        let line_height = self.line_height(text.size);
+        let mut glyphs = vec![];
+        let mut widest: f32 = 0.0;
+        for (i, line) in lines.iter().enumerate() {
+            let line_width = width(line);
+            widest = widest.max(line_width);
+            let mut x = match text.align {
+                Align::Left => 0.0,
+                Align::Center => -line_width / 2.0,
+                Align::Right => -line_width
+            };
+            let baseline = i as f32 * line_height + scaled.ascent();
+            let mut previous: Option<GlyphId> = None;
+            for c in line.chars() {
+                let id = scaled.glyph_id(c);
+                if let Some(previous) = previous {
+                    x += scaled.kern(previous, id);
+                }
+                glyphs.push(PlacedGlyph { id, pen: [x, baseline] });
+                x += scaled.h_advance(id);
+                previous = Some(id);
+            }
+        }
+
+        Layout {
+            glyphs,
+            lines: lines.len(),
+            size: [widest, lines.len() as f32 * line_height]
+        }
+    }
+
+    pub fn measure(&self, text: &Text) -> [f32; 2] {
+        self.layout(text).size
+    }
+
+}
+
+impl Default for Font {
+    fn default() -> Self {
+        Self::from_bytes(DEFAULT_FONT.to_vec()).unwrap()
+    }
+}
+
+#[derive(Debug)]
+pub enum FontError {
+    Invalid
+}
+
+impl std::fmt::Display for FontError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Invalid => write!(f, "Font data is not a valid TrueType or OpenType font")
+        }
+    }
+}
+
+// which of a Typesetter's fonts a Text is set in; FontId::default() is the
+// bundled DejaVu Sans
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FontId(usize);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Text {
+    content: String,
+    font: FontId,
+    // in pixels from the top of the tallest glyph to the bottom of the lowest
+    size: f32,
+    color: Color,
+    max_width: Option<f32>,
+    align: Align
+}
+
+impl Text {
+
+    pub fn new(content: &str, size: f32) -> Text {
+        Self {
+            content: content.to_string(),
+            font: FontId::default(),
+            size,
+            color: WHITE,
+            max_width: None,
+            align: Align::default()
+        }
+    }
+
+    pub fn font(mut self, font: FontId) -> Text {
+        self.font = font;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Text {
+        self.color = color;
+        self
+    }
+
+    pub fn max_width(mut self, max_width: f32) -> Text {
+        self.max_width = Some(max_width);
+        self
+    }
+
+    pub fn align(mut self, align: Align) -> Text {
+        self.align = align;
+        self
+    }
+
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub id: GlyphId,
+    // on the baseline, relative to the anchor with y pointing down
+    pub pen: [f32; 2]
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub glyphs: Vec<PlacedGlyph>,
+    pub lines: usize,
+    pub size: [f32; 2]
+}
+
+// where a rasterized glyph sits in the atlas, and where its top left corner
+// sits relative to the pen, y pointing down
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Slot {
+    corner: [u32; 2],
+    size: [u32; 2],
+    offset: [f32; 2]
+}
+
+// Rasterized glyphs packed into one texture in shelves: rows as tall as the
+// tallest glyph on them, filled left to right. The texture only ever grows
+// downwards, so texels handed out earlier stay valid.
+pub struct GlyphAtlas {
+    image: Image,
+    // None for glyphs without an outline, like spaces
+    slots: HashMap<(FontId, GlyphId, u32), Option<Slot>>,
+    // where the next glyph goes and the height of the shelf it goes on
+    cursor: [u32; 2],
+    shelf_height: u32,
+    // whether the image changed since it was last taken
+    dirty: bool
+}
+
+impl GlyphAtlas {
+
+    pub fn new() -> GlyphAtlas {
+        Self {
+            image: Self::blank(INITIAL_ATLAS_HEIGHT),
+            slots: HashMap::new(),
+            cursor: [PADDING, PADDING],
+            shelf_height: 0,
+            dirty: true
+        }
+    }
+
+    // white everywhere so filtered edges fade out rather than darken
+    fn blank(height: u32) -> Image {
+        Image::from_rgba(ATLAS_WIDTH, height, [255, 255, 255, 0].repeat((ATLAS_WIDTH * height) as usize))
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    // the image, if it changed since the last call
+    pub fn take_changes(&mut self) -> Option<&Image> {
+        std::mem::take(&mut self.dirty).then_some(&self.image)
+    }
+
+    fn slot(&mut self, font: (FontId, &Font), id: GlyphId, size: f32) -> Option<Slot> {
+
+        let key = (font.0, id, size.to_bits());
+        if let Some(slot) = self.slots.get(&key) {
+            return *slot;
+        }
+
+        let glyph = id.with_scale(PxScale::from(size));
+        let slot = font.1.0.outline_glyph(glyph).and_then(|outline| {
+            let bounds = outline.px_bounds();
+            let size = [bounds.width() as u32, bounds.height() as u32];
+            let corner = self.allocate(size)?;
+            outline.draw(|x, y, coverage| {
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+                self.image.set_pixel(corner[0] + x, corner[1] + y, [255, 255, 255, alpha]);
+            });
+            self.dirty = true;
+            Some(Slot { corner, size, offset: [bounds.min.x, bounds.min.y] })
+        });
+        self.slots.insert(key, slot);
+        slot
+    }
+
+    // the corner of a free `size` region, growing the image if it is full;
+    // None if the glyph is wider than the atlas
+    fn allocate(&mut self, size: [u32; 2]) -> Option<[u32; 2]> {
+
+        if size[0] + 2 * PADDING > ATLAS_WIDTH {
+            return None;
+        }
+        if self.cursor[0] + size[0] + PADDING > ATLAS_WIDTH {
+            self.cursor = [PADDING, self.cursor[1] + self.shelf_height + PADDING];
+            self.shelf_height = 0;
+        }
+        while self.cursor[1] + size[1] + PADDING > self.image.height() {
+            let height = self.image.height() * 2;
+            let mut pixels = self.image.pixels().to_vec();
+            pixels.extend(Self::blank(height - self.image.height()).pixels());
+            self.image = Image::from_rgba(ATLAS_WIDTH, height, pixels);
+        }
+
+        let corner = self.cursor;
+        self.cursor[0] += size[0] + PADDING;
+        self.shelf_height = self.shelf_height.max(size[1]);
+        Some(corner)
+    }
+
+}
+
+impl Default for GlyphAtlas {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Turns Text into textured quads, rasterizing glyphs into a shared atlas the
+// first time they are needed at a given size.
+pub struct Typesetter {
+    fonts: Vec<Font>,
+    atlas: GlyphAtlas
+}
+
+impl Typesetter {
+
+    pub fn new() -> Typesetter {
+        Self {
+            fonts: vec![Font::default()],
+            atlas: GlyphAtlas::new()
+        }
+    }
+
+    pub fn add_font(&mut self, font: Font) -> FontId {
+        self.fonts.push(font);
+        FontId(self.fonts.len() - 1)
+    }
+
+    pub fn font(&self, id: FontId) -> &Font {
+        &self.fonts[id.0]
+    }
+
+    pub fn atlas(&self) -> &GlyphAtlas {
+        &self.atlas
+    }
+
+    pub fn atlas_mut(&mut self) -> &mut GlyphAtlas {
+        &mut self.atlas
+    }
+
+    pub fn measure(&self, text: &Text) -> [f32; 2] {
+        self.font(text.font).measure(text)
+    }
+
+    // two triangles per visible glyph, sampling `texture`, which must hold
+    // the atlas; `anchor` is where the text's top meets its alignment edge.
+    // None if nothing in `text` is visible
+    pub fn entity(&mut self, text: &Text, anchor: [f32; 2], texture: TextureId) -> Option<Entity> {
+
+        let font = &self.fonts[text.font.0];
+        let layout = font.layout(text);
+        let mut vertices = Vec::with_capacity(6 * layout.glyphs.len());
+
+        for glyph in layout.glyphs {
+            let Some(slot) = self.atlas.slot((text.font, font), glyph.id, text.size) else {
+                continue;
+            };
+            // whole pixels keep glyphs crisp at the default zoom
+            let left = (anchor[0] + glyph.pen[0]).round() + slot.offset[0];
+            let top = (anchor[1] - glyph.pen[1]).round() - slot.offset[1];
+            let (right, bottom) = (left + slot.size[0] as f32, top - slot.size[1] as f32);
+            let [u0, v0] = slot.corner.map(|texel| texel as f32);
+            let (u1, v1) = (u0 + slot.size[0] as f32, v0 + slot.size[1] as f32);
+
+            let corner = |x: f32, y: f32, u: f32, v: f32| Vertex::new(x, y, 0.0, text.color).with_texel(u, v);
+            let (top_left, bottom_left) = (corner(left, top, u0, v0), corner(left, bottom, u0, v1));
+            let (bottom_right, top_right) = (corner(right, bottom, u1, v1), corner(right, top, u1, v0));
+            vertices.extend([top_left, bottom_left, bottom_right, top_left, bottom_right, top_right]);
+        }
+
+        (!vertices.is_empty()).then(|| EntityBuilder::from_triangles(vertices).textured(texture).build())
+    }
+
+}
+
+impl Default for Typesetter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn layout_wraps_at_spaces_and_newlines() {
+        let font = Font::default();
+        let one_line = font.layout(&Text::new("wrap these words", 20.0));
+        assert_eq!(one_line.lines, 1);
+        let wrapped = font.layout(&Text::new("wrap these words\nand this", 20.0).max_width(one_line.size[0] - 1.0));
+        assert_eq!(wrapped.lines, 3);
+        assert!(wrapped.size[0] < one_line.size[0]);
+        assert_eq!(wrapped.size[1], 3.0 * font.line_height(20.0));
+        // "words" starts the second line
+        let second = wrapped.glyphs.iter().find(|glyph| glyph.pen[1] > wrapped.glyphs[0].pen[1]).unwrap();
+        assert_eq!(second.pen[0], 0.0);
+        assert_eq!(second.id, font.0.glyph_id('w'));
+    }
+
+    #[test]
+    fn layout_applies_kerning() {
+        let font = Font::default();
+        let scaled = font.0.as_scaled(PxScale::from(40.0));
+        let (a, v) = (scaled.glyph_id('A'), scaled.glyph_id('V'));
+        assert!(scaled.kern(a, v) < 0.0);
+        let layout = font.layout(&Text::new("AV", 40.0));
+        assert_eq!(layout.glyphs[1].pen[0], scaled.h_advance(a) + scaled.kern(a, v));
+        let centered = font.layout(&Text::new("AV", 40.0).align(Align::Center));
+        assert_eq!(centered.glyphs[0].pen[0], -layout.size[0] / 2.0);
+    }
+
+    #[test]
+    fn atlas_packs_in_shelves_and_grows() {
+        let mut atlas = GlyphAtlas::new();
+        assert_eq!(atlas.allocate([300, 20]), Some([1, 1]));
+        assert_eq!(atlas.allocate([200, 30]), Some([302, 1]));
+        // too wide for what's left of the shelf
+        assert_eq!(atlas.allocate([20, 10]), Some([1, 32]));
+        assert_eq!(atlas.allocate([600, 10]), None);
+        assert_eq!(atlas.allocate([10, 200]), Some([22, 32]));
+        assert_eq!(atlas.image().height(), 256);
+    }
+
+    #[test]
+    fn glyphs_are_rasterized_once() {
+        let mut typesetter = Typesetter::new();
+        let entity = typesetter.entity(&Text::new("a", 30.0), [0.0, 0.0], TextureId(0)).unwrap();
+        assert_eq!(entity.vertices.len(), 6);
+        assert!(typesetter.atlas_mut().take_changes().is_some());
+        assert!(typesetter.atlas().image().pixels().chunks(4).any(|texel| texel[3] == 255));
+        // a space has nothing to draw, and the "a" is already in the atlas
+        typesetter.entity(&Text::new("a a", 30.0), [0.0, 0.0], TextureId(0)).unwrap();
+        assert!(typesetter.atlas_mut().take_changes().is_none());
+    }
+
+}
@@ -0,0 +1,43 @@
+// Vertex shader
+struct Globals {
+	view_projection: mat4x4<f32>
+}
+
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+@group(1) @binding(0)
+var image: texture_2d<f32>;
+@group(1) @binding(1)
+var image_sampler: sampler;
+
+struct VertexIn {
+	@location(0) position: vec3<f32>,
+	@location(1) color: vec3<f32>,
+	@location(5) texel: vec2<f32>
+}
+
+struct VertexOut {
+	@builtin(position) position: vec4<f32>,
+	@location(0) color: vec3<f32>,
+	@location(1) texel: vec2<f32>
+}
+
+@vertex
+fn vertex_shader_main(model: VertexIn) -> VertexOut {
+	var out: VertexOut;
+	out.position = globals.view_projection * vec4<f32>(model.position, 1.0);
+	out.color = model.color;
+	out.texel = model.texel;
+	return out;
+}
+
+// Fragment shader
+// texels rather than 0..1 coordinates, so a texture can grow without
+// invalidating the vertices already pointing into it
+@fragment
+fn fragment_shader_main(in: VertexOut) -> @location(0) vec4<f32> {
+	let size = vec2<f32>(textureDimensions(image));
+	let sampled = textureSample(image, image_sampler, in.texel / size);
+	return vec4<f32>(in.color * sampled.rgb, sampled.a);
+}
@@ -0,0 +1,115 @@
+// A 2D affine transform stored as the column-major 4x4 matrix WGSL expects.
+// Depth is always passed through untouched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    columns: [[f32; 4]; 4]
+}
+
+impl Transform {
+
+    pub const IDENTITY: Transform = Transform {
+        columns: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0]
+        ]
+    };
+
+    fn affine(a: f32, b: f32, c: f32, d: f32, tx: f32, ty: f32) -> Transform {
+        // | a c tx |
+        // | b d ty |
+        Self {
+            columns: [
+                [a, b, 0.0, 0.0],
+                [c, d, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [tx, ty, 0.0, 1.0]
+            ]
+        }
+    }
+
+    pub fn translation(x: f32, y: f32) -> Transform {
+        Self::affine(1.0, 0.0, 0.0, 1.0, x, y)
+    }
+
+    pub fn scale(x: f32, y: f32) -> Transform {
+        Self::affine(x, 0.0, 0.0, y, 0.0, 0.0)
+    }
+
+    pub fn rotation(radians: f32) -> Transform {
+        let (sin, cos) = radians.sin_cos();
+        Self::affine(cos, sin, -sin, cos, 0.0, 0.0)
+    }
+
+    // maps a width x height region centered on the origin onto clip space
+    pub fn orthographic(width: f32, height: f32) -> Transform {
+        Self::scale(2.0 / width, 2.0 / height)
+    }
+
+    // `self` followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        let [a, b, c, d, tx, ty] = self.coefficients();
+        let [na, nb, nc, nd, ntx, nty] = next.coefficients();
+        Self::affine(
+            na * a + nc * b,
+            nb * a + nd * b,
+            na * c + nc * d,
+            nb * c + nd * d,
+            na * tx + nc * ty + ntx,
+            nb * tx + nd * ty + nty
+        )
+    }
+
+    pub fn inverse(&self) -> Transform {
+        let [a, b, c, d, tx, ty] = self.coefficients();
+        let determinant = a * d - b * c;
+        if determinant == 0.0 {
+            panic!("transform is singular and cannot be inverted")
+        }
+        let (ia, ib, ic, id) = (d / determinant, -b / determinant, -c / determinant, a / determinant);
+        Self::affine(ia, ib, ic, id, -(ia * tx + ic * ty), -(ib * tx + id * ty))
+    }
+
+    pub fn apply(&self, point: [f32; 2]) -> [f32; 2] {
+        let [a, b, c, d, tx, ty] = self.coefficients();
+        [a * point[0] + c * point[1] + tx, b * point[0] + d * point[1] + ty]
+    }
+
+    pub fn columns(&self) -> [[f32; 4]; 4] {
+        self.columns
+    }
+
+    fn coefficients(&self) -> [f32; 6] {
+        let [first, second, _, translation] = self.columns;
+        [first[0], first[1], second[0], second[1], translation[0], translation[1]]
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        (a[0] - b[0]).abs() < 1e-4 && (a[1] - b[1]).abs() < 1e-4
+    }
+
+    #[test]
+    fn transforms_compose_in_order() {
+        let turn_then_move = Transform::rotation(std::f32::consts::FRAC_PI_2).then(&Transform::translation(10.0, 0.0));
+        assert!(close(turn_then_move.apply([1.0, 0.0]), [10.0, 1.0]));
+        let move_then_turn = Transform::translation(10.0, 0.0).then(&Transform::rotation(std::f32::consts::FRAC_PI_2));
+        assert!(close(move_then_turn.apply([1.0, 0.0]), [0.0, 11.0]));
+    }
+
+    #[test]
+    fn inverse_undoes_the_transform() {
+        let transform = Transform::translation(3.0, -7.0).then(&Transform::rotation(0.6)).then(&Transform::scale(2.5, 0.5));
+        let point = [12.0, -4.5];
+        assert!(close(transform.inverse().apply(transform.apply(point)), point));
+        assert!(close(transform.then(&transform.inverse()).apply(point), point));
+    }
+
+}